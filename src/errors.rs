@@ -25,6 +25,9 @@ pub enum Error {
     /// XCB errors.
     #[error(transparent)]
     Xcb(XcbError),
+    /// I/O errors, such as from the control socket or the event loop.
+    #[error(transparent)]
+    Io(std::io::Error),
     /// Other errors.
     #[error("{0}")]
     Other(String),
@@ -39,4 +42,26 @@ impl<T: Into<XcbError>> From<T> for Error {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(other: std::io::Error) -> Self {
+        Self::Io(other)
+    }
+}
+
+impl Error {
+    /// Check if the error was caused by a request on a window that no longer exists.
+    ///
+    /// Clients can destroy their windows at any time, so this is expected for
+    /// requests that race with a client going away.
+    pub fn is_bad_window(&self) -> bool {
+        let error = match self {
+            Self::Xcb(XcbError::Generic(e)) => e,
+            Self::Xcb(XcbError::Reply(xcb::ReplyError::GenericError(e))) => e,
+            _ => return false,
+        };
+        // Queries on a destroyed window fail with `BadDrawable` instead.
+        matches!(error.error_code(), xcb::WINDOW | xcb::DRAWABLE)
+    }
+}
+
 pub type NerdResult<T> = Result<T, Error>;
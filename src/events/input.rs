@@ -4,13 +4,21 @@
 
 use serde::{Deserialize, Serialize};
 
+use super::keyconvert;
+use crate::prelude::*;
+
 /// Modifier masks.
+///
+/// `Alt`, `Meta`, `Super` and `Hyper` are named after keys rather than
+/// modifiers. They must be resolved with a [`ModifierMap`] to whichever of
+/// `Mod1` to `Mod5` the key is mapped to before being used as a mask.
 #[repr(u32)]
 #[non_exhaustive]
-#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
 pub enum ModMask {
     Shift = 0x01,
     Lock = 0x02,
+    #[serde(alias = "Ctrl")]
     Control = 0x04,
     /// Usually `Alt`.
     Mod1 = 0x08,
@@ -21,6 +29,103 @@ pub enum ModMask {
     /// Usually `Super`.
     Mod4 = 0x40,
     Mod5 = 0x80,
+    Alt = 0x100,
+    Meta = 0x200,
+    Super = 0x400,
+    Hyper = 0x800,
+}
+
+impl ModMask {
+    /// Replace a modifier named after a key with the modifier the key is mapped to.
+    pub fn resolve(self, map: &ModifierMap) -> Self {
+        match self {
+            Self::Alt => map.alt,
+            Self::Meta => map.meta,
+            Self::Super => map.super_,
+            Self::Hyper => map.hyper,
+            m => m,
+        }
+    }
+}
+
+/// Modifiers that the `Alt`, `Meta`, `Super` and `Hyper` keys are mapped to.
+#[derive(Debug, Clone, Copy)]
+pub struct ModifierMap {
+    alt: ModMask,
+    meta: ModMask,
+    super_: ModMask,
+    hyper: ModMask,
+}
+
+impl ModifierMap {
+    /// Read the modifier mapping of the server.
+    ///
+    /// Keys that aren't mapped to any of `Mod1` to `Mod5` keep their usual modifier.
+    pub fn query(conn: &xcb::Connection, keysyms: &keyconvert::KeySymbols) -> NerdResult<Self> {
+        let slots = get_modifier_slots(conn)?;
+        let find = |left: Key, right: Key, default: ModMask| {
+            [left, right]
+                .into_iter()
+                .find_map(|k| get_keysym_modifier(&slots, keysyms, k as xcb::Keysym))
+                .unwrap_or(default)
+        };
+
+        let default = Self::default();
+        Ok(Self {
+            alt: find(Key::XK_Alt_L, Key::XK_Alt_R, default.alt),
+            meta: find(Key::XK_Meta_L, Key::XK_Meta_R, default.meta),
+            super_: find(Key::XK_Super_L, Key::XK_Super_R, default.super_),
+            hyper: find(Key::XK_Hyper_L, Key::XK_Hyper_R, default.hyper),
+        })
+    }
+}
+
+impl Default for ModifierMap {
+    /// The usual mapping, with `Alt` and `Meta` on `Mod1`, and `Super` and `Hyper` on `Mod4`.
+    fn default() -> Self {
+        Self {
+            alt: ModMask::Mod1,
+            meta: ModMask::Mod1,
+            super_: ModMask::Mod4,
+            hyper: ModMask::Mod4,
+        }
+    }
+}
+
+/// Get the keycodes occupying each modifier slot, from `Shift` to `Mod5`.
+pub fn get_modifier_slots(conn: &xcb::Connection) -> NerdResult<Vec<Vec<xcb::Keycode>>> {
+    let reply = xcb::get_modifier_mapping(conn).get_reply()?;
+    let per_modifier = (reply.keycodes_per_modifier() as usize).max(1);
+    Ok(reply
+        .keycodes()
+        .chunks(per_modifier)
+        .map(|keycodes| keycodes.iter().copied().filter(|&k| k != 0).collect())
+        .collect())
+}
+
+/// Find which of `Mod1` to `Mod5` a key is mapped to, given the modifier
+/// slots returned by [`get_modifier_slots`].
+pub fn get_keysym_modifier(
+    slots: &[Vec<xcb::Keycode>],
+    keysyms: &keyconvert::KeySymbols,
+    keysym: xcb::Keysym,
+) -> Option<ModMask> {
+    const MODS: [ModMask; 5] = [
+        ModMask::Mod1,
+        ModMask::Mod2,
+        ModMask::Mod3,
+        ModMask::Mod4,
+        ModMask::Mod5,
+    ];
+
+    let keycodes: Vec<_> = keysyms.get_keycode(keysym).collect();
+    // The first three slots are Shift, Lock and Control.
+    slots
+        .iter()
+        .skip(3)
+        .zip(MODS)
+        .find(|(slot, _)| slot.iter().any(|k| keycodes.contains(k)))
+        .map(|(_, modifier)| modifier)
 }
 
 /// Mouse buttons.
@@ -34,10 +139,19 @@ pub enum Button {
     Button2 = 0x02,
     /// Usually the Right button.
     Button3 = 0x03,
+    /// Usually scrolling the wheel up.
+    #[serde(alias = "ScrollUp")]
     Button4 = 0x04,
+    /// Usually scrolling the wheel down.
+    #[serde(alias = "ScrollDown")]
     Button5 = 0x05,
 }
 
+/// Check if `keysym` belongs to a modifier key, from `XK_Shift_L` to `XK_Hyper_R`.
+pub fn is_modifier(keysym: u32) -> bool {
+    (Key::XK_Shift_L as u32..=Key::XK_Hyper_R as u32).contains(&keysym)
+}
+
 /// Keyboard keys.
 #[repr(u32)]
 #[non_exhaustive]
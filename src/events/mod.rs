@@ -1,11 +1,23 @@
 //! X event utilities.
 
 use crate::prelude::*;
+use std::os::unix::io::{AsRawFd, RawFd};
 use std::sync::Arc;
 
+use tokio::io::unix::AsyncFd;
+
 pub mod input;
 pub mod keyconvert;
 
+/// Column of the keyboard mapping that key events are looked up in.
+///
+/// Each keycode maps to a row of keysyms. Column `0` holds the keysym the key
+/// produces without modifiers and column `1` the one it produces with `Shift`,
+/// while columns `2` and `3` are the same for the second keyboard group.
+/// Bindings name the unshifted keysym and list `Shift` among their modifiers,
+/// so key events always report column `0`, whichever modifiers are held.
+const KEYSYM_COLUMN: i32 = 0;
+
 /// Wrapper containing an [`xcb::KeyPressEvent`] and it's corresponding
 /// keysym for a specific connection.
 pub struct KeyPressEvent {
@@ -42,6 +54,37 @@ impl KeyReleaseEvent {
     }
 }
 
+/// Wrapper containing an [`xcb::PropertyNotifyEvent`] and the connection
+/// it was received on.
+///
+/// Handlers compare the property's atom with atoms they already know. Its
+/// name is only looked up when the event is printed.
+pub struct PropertyNotifyEvent {
+    pub base: xcb::PropertyNotifyEvent,
+    conn: Arc<xcb::Connection>,
+}
+
+impl PropertyNotifyEvent {
+    pub fn new(base: xcb::PropertyNotifyEvent, conn: Arc<xcb::Connection>) -> Self {
+        Self { base, conn }
+    }
+
+    /// Atom identifying the property.
+    pub fn atom(&self) -> xcb::Atom {
+        self.base.atom()
+    }
+
+    /// Ask the server for the name of the property.
+    ///
+    /// This waits for a reply, so it should only be used for logging.
+    fn get_name(&self) -> NerdResult<String> {
+        Ok(xcb::get_atom_name(&self.conn, self.atom())
+            .get_reply()?
+            .name()
+            .to_owned())
+    }
+}
+
 /// (Incomplete) list of events propagated by the X server.
 #[non_exhaustive]
 pub enum Event {
@@ -53,6 +96,8 @@ pub enum Event {
     WindowMapRequest(xcb::MapRequestEvent),
     WindowUnmap(xcb::UnmapNotifyEvent),
     WindowConfigureRequest(xcb::ConfigureRequestEvent),
+    WindowExpose(xcb::ExposeEvent),
+    PropertyNotify(PropertyNotifyEvent),
 
     ButtonPress(xcb::ButtonPressEvent),
     ButtonRelease(xcb::ButtonReleaseEvent),
@@ -63,18 +108,29 @@ pub enum Event {
 }
 
 /// Helper for converting received events into native types.
+///
+/// Events are read without blocking. Use [`EventManager::wait_for_events`] to
+/// wait for the connection to become readable, and then drain all available
+/// events with [`EventManager::poll_event`] in the order they were received.
 pub struct EventManager {
     conn: Arc<xcb::Connection>,
     keysyms: keyconvert::KeySymbols,
+    /// Connection file descriptor, registered with the async runtime.
+    fd: AsyncFd<RawFd>,
 }
 
 impl EventManager {
-    #[must_use]
-    pub fn new(conn: Arc<xcb::Connection>) -> Self {
-        Self {
+    /// Create a new event manager.
+    ///
+    /// This must be called from within a tokio runtime.
+    pub fn new(conn: Arc<xcb::Connection>) -> NerdResult<Self> {
+        let fd = AsyncFd::new(conn.as_raw_fd())?;
+
+        Ok(Self {
             conn: conn.clone(),
             keysyms: keyconvert::KeySymbols::new(&conn),
-        }
+            fd,
+        })
     }
 
     /// Get keysymbols
@@ -82,37 +138,105 @@ impl EventManager {
         &self.keysyms
     }
 
-    /// Wait for an event from the connection.
-    pub fn get_event(&self) -> NerdResult<Event> {
-        let event = match self.conn.wait_for_event() {
-            Some(e) => e,
-            None => return Err(Error::Static("event not received")),
-        };
-
-        Ok(match event.response_type() {
-            xcb::CLIENT_MESSAGE => Event::ClientMessage(unsafe { std::mem::transmute(event) }),
-            xcb::CREATE_NOTIFY => Event::WindowCreate(unsafe { std::mem::transmute(event) }),
-            xcb::DESTROY_NOTIFY => Event::WindowDestroy(unsafe { std::mem::transmute(event) }),
-            xcb::MAP_REQUEST => Event::WindowMapRequest(unsafe { std::mem::transmute(event) }),
-            xcb::UNMAP_NOTIFY => Event::WindowUnmap(unsafe { std::mem::transmute(event) }),
+    /// Ask the server where the pointer is.
+    ///
+    /// Returns the pointer position relative to `root`, and the child of
+    /// `root` under the pointer, which is [`xcb::NONE`] if there is none.
+    pub fn query_pointer(&self, root: xcb::Window) -> NerdResult<(i16, i16, xcb::Window)> {
+        let reply = xcb::query_pointer(&self.conn, root).get_reply()?;
+        Ok((reply.root_x(), reply.root_y(), reply.child()))
+    }
+
+    /// Wait until there is new data to be read from the connection.
+    ///
+    /// Events that xcb has already read into its queue (for example, while waiting
+    /// for a reply) do not make the connection readable again, so all events should be
+    /// drained with [`EventManager::poll_event`] before calling this.
+    pub async fn wait_for_events(&self) -> NerdResult<()> {
+        let mut guard = self.fd.readable().await?;
+
+        // Any data arriving after this will set the readiness again, so
+        // it is safe to clear before the events are actually read.
+        guard.clear_ready();
+        Ok(())
+    }
+
+    /// Get the next event that has been received from the connection, without blocking.
+    ///
+    /// Events are returned in the order they were received. Returns [`None`]
+    /// if no events are available.
+    pub fn poll_event(&self) -> NerdResult<Option<Event>> {
+        match self.conn.poll_for_event() {
+            Some(e) => Ok(Some(self.convert(e))),
+            None => {
+                self.conn.has_error()?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Convert a raw event into a native type.
+    ///
+    /// Events sent by other clients with `SendEvent` are converted like any other.
+    fn convert(&self, event: xcb::GenericEvent) -> Event {
+        // The highest bit is set on events sent with `SendEvent`.
+        match event.response_type() & !0x80 {
+            xcb::CLIENT_MESSAGE => {
+                let event: xcb::ClientMessageEvent = unsafe { std::mem::transmute(event) };
+                Event::ClientMessage(event)
+            }
+            xcb::CREATE_NOTIFY => {
+                let event: xcb::CreateNotifyEvent = unsafe { std::mem::transmute(event) };
+                Event::WindowCreate(event)
+            }
+            xcb::DESTROY_NOTIFY => {
+                let event: xcb::DestroyNotifyEvent = unsafe { std::mem::transmute(event) };
+                Event::WindowDestroy(event)
+            }
+            xcb::MAP_REQUEST => {
+                let event: xcb::MapRequestEvent = unsafe { std::mem::transmute(event) };
+                Event::WindowMapRequest(event)
+            }
+            xcb::UNMAP_NOTIFY => {
+                let event: xcb::UnmapNotifyEvent = unsafe { std::mem::transmute(event) };
+                Event::WindowUnmap(event)
+            }
             xcb::CONFIGURE_REQUEST => {
-                Event::WindowConfigureRequest(unsafe { std::mem::transmute(event) })
+                let event: xcb::ConfigureRequestEvent = unsafe { std::mem::transmute(event) };
+                Event::WindowConfigureRequest(event)
+            }
+            xcb::EXPOSE => {
+                let event: xcb::ExposeEvent = unsafe { std::mem::transmute(event) };
+                Event::WindowExpose(event)
+            }
+            xcb::PROPERTY_NOTIFY => {
+                let event: xcb::PropertyNotifyEvent = unsafe { std::mem::transmute(event) };
+                Event::PropertyNotify(PropertyNotifyEvent::new(event, self.conn.clone()))
+            }
+            xcb::BUTTON_PRESS => {
+                let event: xcb::ButtonPressEvent = unsafe { std::mem::transmute(event) };
+                Event::ButtonPress(event)
+            }
+            xcb::BUTTON_RELEASE => {
+                let event: xcb::ButtonReleaseEvent = unsafe { std::mem::transmute(event) };
+                Event::ButtonRelease(event)
             }
-            xcb::BUTTON_PRESS => Event::ButtonPress(unsafe { std::mem::transmute(event) }),
-            xcb::BUTTON_RELEASE => Event::ButtonRelease(unsafe { std::mem::transmute(event) }),
             xcb::KEY_PRESS => {
                 let event: xcb::KeyPressEvent = unsafe { std::mem::transmute(event) };
-                let keysym = self.keysyms.press_lookup_keysym(&event, 1);
+                let keysym = self.keysyms.press_lookup_keysym(&event, KEYSYM_COLUMN);
                 Event::KeyPress(KeyPressEvent::new(event, keysym))
             }
             xcb::KEY_RELEASE => {
                 let event: xcb::KeyReleaseEvent = unsafe { std::mem::transmute(event) };
-                let keysym = self.keysyms.press_lookup_keysym(&event, 1);
+                let keysym = self.keysyms.release_lookup_keysym(&event, KEYSYM_COLUMN);
                 Event::KeyRelease(KeyReleaseEvent::new(event, keysym))
             }
-            xcb::MOTION_NOTIFY => Event::PointerMotion(unsafe { std::mem::transmute(event) }),
+            xcb::MOTION_NOTIFY => {
+                let event: xcb::MotionNotifyEvent = unsafe { std::mem::transmute(event) };
+                Event::PointerMotion(event)
+            }
             _ => Event::Unknown,
-        })
+        }
     }
 }
 
@@ -140,6 +264,21 @@ impl std::fmt::Debug for Event {
             Self::WindowConfigureRequest(e) => {
                 write!(f, "Window configure [window: {}]", e.window())?;
             }
+            Self::WindowExpose(e) => {
+                write!(f, "Window expose [window: {}]", e.window())?;
+            }
+            Self::PropertyNotify(e) => {
+                write!(
+                    f,
+                    "Property notify [window: {}, atom: {}",
+                    e.base.window(),
+                    e.atom()
+                )?;
+                match e.get_name() {
+                    Ok(name) => write!(f, " ({})]", name)?,
+                    Err(err) => write!(f, " (unknown name: {})]", err)?,
+                }
+            }
             Self::ButtonPress(e) => {
                 write!(
                     f,
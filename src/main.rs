@@ -11,17 +11,23 @@ mod wm;
 
 use prelude::*;
 
+/// Get the directory log files are written to, `$XDG_CACHE_HOME/nerdwm/logs`.
+fn get_log_dir() -> std::path::PathBuf {
+    get_xdg_dirs().get_cache_home().join("logs")
+}
+
 /// Configure file logging.
 ///
-/// This creates a new [`fern`] logger, with the [`LevelFilter`] set to
-/// [`LevelFilter::Trace`] (when debug assertions are turned on) or else
-/// [`LevelFilter::Info`], and writes to the path
-/// `$XDG_CACHE_HOME/nerdwm/logs/nerdwm-{timestamp}.log`
+/// This creates a new [`fern`] logger, which writes to the path
+/// `$XDG_CACHE_HOME/nerdwm/logs/nerdwm-{timestamp}.log`.
+///
+/// The level is read from `NERDWM_LOG` if it is set, or else defaults to
+/// [`log::LevelFilter::Trace`] (when debug assertions are turned on) or
+/// [`log::LevelFilter::Info`]. The config can change it once it is loaded.
 fn setup_logger() {
     // TODO: propagate `Result`s, and some kind of fallback?
 
-    let mut log_path = get_xdg_dirs().get_cache_home();
-    log_path.push("logs");
+    let mut log_path = get_log_dir();
 
     if !log_path.exists() {
         std::fs::create_dir_all(&log_path).unwrap();
@@ -35,12 +41,6 @@ fn setup_logger() {
         )[..],
     );
 
-    #[cfg(debug_assertions)]
-    let current_log_level = log::LevelFilter::Trace;
-
-    #[cfg(not(debug_assertions))]
-    let current_log_level = log::LevelFilter::Trace;
-
     fern::Dispatch::new()
         .format(|out, message, record| {
             out.finish(format_args!(
@@ -51,10 +51,51 @@ fn setup_logger() {
                 message
             ));
         })
-        .level(current_log_level)
+        // Filtering is left to `log::set_max_level`, so the level can change later.
+        .level(log::LevelFilter::Trace)
         .chain(fern::log_file(log_path).unwrap())
         .apply()
         .unwrap();
+
+    let level = wm::config::get_env_log_level();
+    if level.is_none() && std::env::var_os(wm::config::LOG_LEVEL_VAR).is_some() {
+        warn!("Ignoring invalid {}", wm::config::LOG_LEVEL_VAR);
+    }
+    log::set_max_level(level.unwrap_or_else(wm::config::get_default_log_level));
+}
+
+/// Delete all but the `keep` most recent log files, including the current one.
+///
+/// Log files are named after the time they were created, so sorting them by
+/// name sorts them by age. Failures are only logged.
+fn prune_logs(keep: usize) {
+    let dir = get_log_dir();
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(e) => e,
+        Err(e) => {
+            warn!("Unable to read log directory {:?}: {}", dir, e);
+            return;
+        }
+    };
+
+    let mut logs: Vec<_> = entries
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("nerdwm-") && n.ends_with(".log"))
+        })
+        .collect();
+    logs.sort();
+
+    let excess = logs.len().saturating_sub(keep.max(1));
+    for path in &logs[..excess] {
+        match std::fs::remove_file(path) {
+            Ok(()) => trace!("Removed old log {:?}", path),
+            Err(e) => warn!("Unable to remove old log {:?}: {}", path, e),
+        }
+    }
 }
 
 /// Set a new panic hook.
@@ -67,11 +108,65 @@ fn setup_panic() {
     }));
 }
 
+/// Load the config and report any problems with it, without starting the window manager.
+///
+/// Keys are checked against the keymap of `$DISPLAY`, if it can be reached.
+/// Returns the exit code.
+fn check_config() -> i32 {
+    let mut config = match wm::config::Config::load() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("nerdwm: {}", e);
+            return 1;
+        }
+    };
+
+    let conn = match xcb::Connection::connect(None) {
+        Ok((conn, _)) => Some(conn),
+        Err(e) => {
+            eprintln!("nerdwm: not checking keys, unable to connect: {}", e);
+            None
+        }
+    };
+    let keysyms = conn.as_ref().map(events::keyconvert::KeySymbols::new);
+
+    let modifiers = match (&conn, &keysyms) {
+        (Some(conn), Some(keysyms)) => {
+            events::input::ModifierMap::query(conn, keysyms).unwrap_or_default()
+        }
+        _ => events::input::ModifierMap::default(),
+    };
+    config.resolve_modifiers(&modifiers);
+
+    let problems = config.check(keysyms.as_ref());
+    for problem in &problems {
+        eprintln!("nerdwm: {}", problem);
+    }
+    if problems.is_empty() {
+        println!("nerdwm: config is valid");
+        0
+    } else {
+        1
+    }
+}
+
 #[tokio::main]
 async fn main() {
+    if std::env::args().skip(1).any(|a| a == "--check-config") {
+        std::process::exit(check_config());
+    }
+
     setup_logger();
     setup_panic();
 
-    let mut manager = wm::WindowManager::new().unwrap();
+    let mut manager = match wm::WindowManager::new() {
+        Ok(m) => m,
+        Err(e) => {
+            error!("Failed to start: {}", e);
+            eprintln!("nerdwm: {}", e);
+            std::process::exit(1);
+        }
+    };
+    prune_logs(manager.get_config().get_keep_logs());
     manager.run().await.unwrap();
 }
@@ -3,20 +3,97 @@
 //! An action can be anything from re-configuring a window's
 //! geometry, to closing or restarting the window manager itself.
 
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+
 use crate::events;
+use crate::prelude::*;
 use serde::{Deserialize, Serialize};
 
 /// Represents all actions the window manager can perform.
 /// Actions are how the window manager and desktops interpret
 /// standard events.
 #[non_exhaustive]
-#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
 pub enum ActionType {
     FloatingWindowMove,
+    /// Resize a window by dragging anywhere in it. The edge or corner nearest
+    /// to where the drag starts is moved.
     FloatingWindowResize,
     /// For internal use.
     WindowFocus,
+    /// Close the focused window, or the window whose close button was clicked.
+    WindowClose,
+    /// Focus the next window on the active desktop.
+    FocusNext,
+    /// Focus the previous window on the active desktop.
+    FocusPrev,
+    /// Focus the window focused before the current one on the active desktop.
+    /// While the binding's modifiers are held, pressing it again walks further
+    /// back through the focus history, which is only updated once they are released.
+    FocusMruNext,
+    /// For internal use. Ends a [`ActionType::FocusMruNext`] cycle.
+    FocusMruCommit,
+    /// Move the focused window to the master position, or swap it with
+    /// the previous master if it already is the master.
+    SwapMaster,
+    /// Swap the focused window with the one above it in the stack.
+    MoveStackUp,
+    /// Swap the focused window with the one below it in the stack.
+    MoveStackDown,
+    /// Grow the master area of the current layout, or the focused window's
+    /// side of its split.
+    IncMasterRatio,
+    /// Shrink the master area of the current layout, or the focused window's
+    /// side of its split.
+    DecMasterRatio,
+    /// Switch the split holding the focused window between side by side
+    /// and one above the other.
+    RotateSplit,
+    /// Swap the two sides of the split holding the focused window.
+    FlipSplit,
+    /// Toggle between the maximized and normal geometry of the focused window,
+    /// or the window whose maximize button was clicked.
+    ToggleMaximize,
+    /// Make the focused window floating, and cover the left half of the desktop
+    /// with it. Snapping it to the left again switches between a half and a third.
+    SnapLeft,
+    /// Like [`ActionType::SnapLeft`], on the right side of the desktop.
+    SnapRight,
+    /// Like [`ActionType::SnapLeft`], on the top of the desktop.
+    SnapTop,
+    /// Like [`ActionType::SnapLeft`], on the bottom of the desktop.
+    SnapBottom,
+    /// Make the focused window floating, and cover the whole desktop with it.
+    SnapMaximize,
+    /// Give a snapped window back the geometry it had before it was snapped,
+    /// and put it back in the layout if it was tiled.
+    Unsnap,
+    /// Toggle between keeping the focused window out of the layout, where it
+    /// stays where it was tiled until moved, and tiling it.
+    ToggleFloating,
+    /// Toggle between keeping the focused window above all other windows,
+    /// and stacking it normally.
+    ToggleAbove,
+    /// Show or hide the scratchpad window, creating it the first time.
+    ToggleScratchpad,
+    /// Switch to the layout after the current one on the active desktop.
+    CycleLayout,
+    /// Focus the monitor to the right of the focused one, wrapping around.
+    FocusMonitorNext,
+    /// Focus the monitor to the left of the focused one, wrapping around.
+    FocusMonitorPrev,
+    /// Move the focused window to the desktop shown on the monitor to the right,
+    /// and follow it there.
+    MoveWindowToMonitorNext,
+    /// Like [`ActionType::MoveWindowToMonitorNext`], to the monitor on the left.
+    MoveWindowToMonitorPrev,
+    /// Switch the focused monitor to its desktop at the given index.
+    SwitchDesktop(usize),
+    /// Run the command configured for the binding.
+    Spawn,
     WindowManagerQuit,
+    /// Restart the window manager in place, keeping clients on their desktops.
     WindowManagerRestart,
 }
 
@@ -29,12 +106,24 @@ pub enum ActionType {
 pub struct Action {
     action: ActionType,
     event: events::Event,
+    command: Option<String>,
 }
 
 impl Action {
     #[must_use]
     pub fn new(action: ActionType, event: events::Event) -> Self {
-        Self { action, event }
+        Self {
+            action,
+            event,
+            command: None,
+        }
+    }
+
+    /// Attach a command to this action, to be run by [`ActionType::Spawn`].
+    #[must_use]
+    pub fn with_command(mut self, command: Option<String>) -> Self {
+        self.command = command;
+        self
     }
 
     /// Get the type of action to perform.
@@ -46,4 +135,53 @@ impl Action {
     pub fn get_event(&self) -> &events::Event {
         &self.event
     }
+
+    /// Get the command associated to this action, if any.
+    pub fn get_command(&self) -> Option<&str> {
+        self.command.as_deref()
+    }
+}
+
+/// Run a command in the background, detached from the window manager.
+///
+/// The command is run with `sh -c`, in a new session so it isn't affected by
+/// signals sent to the window manager's process group, and with its standard
+/// streams redirected to `/dev/null`. The environment, including `DISPLAY`, is
+/// inherited from the window manager.
+///
+/// The command is forked twice, so it is adopted by init instead of being a
+/// child of the window manager. It is then never left as a zombie, even if the
+/// window manager restarts while it is running.
+pub fn spawn(command: &str) -> NerdResult<()> {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    unsafe {
+        cmd.pre_exec(|| {
+            // Detach from the window manager's session.
+            if libc::setsid() == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            // Only the second child runs the command, and the first exits
+            // right away.
+            match libc::fork() {
+                -1 => Err(std::io::Error::last_os_error()),
+                0 => Ok(()),
+                _ => libc::_exit(0),
+            }
+        });
+    }
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| Error::Other(format!("unable to spawn `{}`: {}", command, e)))?;
+    // The first child exits as soon as it has forked, so this doesn't block.
+    child.wait()?;
+
+    info!("Spawned `{}`", command);
+    Ok(())
 }
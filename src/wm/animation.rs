@@ -0,0 +1,60 @@
+//! Animated transitions of clients between two geometries.
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use super::client::ClientWindow;
+use super::geometry::Rectangle;
+
+/// Time between two steps of an animation, for about 60 steps per second.
+pub const FRAME: Duration = Duration::from_millis(16);
+
+/// A client moving from one geometry to another.
+pub struct Animation {
+    client: ClientWindow,
+    from: Rectangle,
+    to: Rectangle,
+    start: Instant,
+    duration: Duration,
+}
+
+impl Animation {
+    /// Start moving `client` from `from` to `to`, over `duration`.
+    #[must_use]
+    pub fn new(client: ClientWindow, from: Rectangle, to: Rectangle, duration: Duration) -> Self {
+        Self {
+            client,
+            from,
+            to,
+            start: Instant::now(),
+            duration,
+        }
+    }
+
+    /// Get the client being moved.
+    pub fn get_client(&self) -> &ClientWindow {
+        &self.client
+    }
+
+    /// Get the geometry the client should have at `now`, along with whether
+    /// the animation is over.
+    pub fn get_geometry(&self, now: Instant) -> (Rectangle, bool) {
+        let elapsed = now.saturating_duration_since(self.start);
+        if elapsed >= self.duration {
+            return (self.to, true);
+        }
+
+        // Ease out, so clients slow down as they reach their place.
+        let t = elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        let t = 1.0 - (1.0 - t).powi(3);
+        let lerp = |a: i32, b: i32| a + ((b - a) as f32 * t).round() as i32;
+        let geometry = Rectangle::new(
+            lerp(self.from.x as i32, self.to.x as i32) as i16,
+            lerp(self.from.y as i32, self.to.y as i32) as i16,
+            lerp(self.from.width as i32, self.to.width as i32) as u16,
+            lerp(self.from.height as i32, self.to.height as i32) as u16,
+        );
+        (geometry, false)
+    }
+}
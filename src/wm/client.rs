@@ -0,0 +1,988 @@
+//! Managed client windows.
+
+use super::config::{BorderConfig, TitlebarConfig};
+use super::geometry::Rectangle;
+use super::stack::OwnsWindows;
+use crate::prelude::*;
+
+/// Geometry of a window, relative to its parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowProperties {
+    root: xcb::Window,
+    x: i16,
+    y: i16,
+    width: u16,
+    height: u16,
+    border_width: u16,
+}
+
+impl WindowProperties {
+    /// Query the geometry of `window`.
+    pub fn query(conn: &xcb::Connection, window: xcb::Window) -> NerdResult<Self> {
+        let geometry = xcb::get_geometry(conn, window).get_reply()?;
+        Ok(Self {
+            root: geometry.root(),
+            x: geometry.x(),
+            y: geometry.y(),
+            width: geometry.width(),
+            height: geometry.height(),
+            border_width: geometry.border_width(),
+        })
+    }
+
+    /// Get the root window of the screen the window is on.
+    pub fn get_root(&self) -> xcb::Window {
+        self.root
+    }
+
+    /// Get the position of the outer corner of the window's border.
+    pub fn get_position(&self) -> (i16, i16) {
+        (self.x, self.y)
+    }
+
+    /// Get the size of the window, excluding its border.
+    pub fn get_size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    /// Get the width of the window's border.
+    pub fn get_border_width(&self) -> u16 {
+        self.border_width
+    }
+
+    /// Get the geometry of the window, including its border.
+    pub fn get_outer_geometry(&self) -> Rectangle {
+        let border = self.border_width * 2;
+        Rectangle::new(self.x, self.y, self.width + border, self.height + border)
+    }
+}
+
+/// Read the `win_gravity` in a window's `WM_NORMAL_HINTS`, which defaults
+/// to [`xcb::GRAVITY_NORTH_WEST`].
+fn get_win_gravity(conn: &xcb::Connection, window: xcb::Window) -> NerdResult<u32> {
+    /// Flag set in `WM_NORMAL_HINTS` if the gravity is given.
+    const P_WIN_GRAVITY: u32 = 1 << 9;
+
+    let reply = xcb::get_property(
+        conn,
+        false,
+        window,
+        xcb::ATOM_WM_NORMAL_HINTS,
+        xcb::ATOM_WM_SIZE_HINTS,
+        0,
+        18,
+    )
+    .get_reply()?;
+
+    if reply.format() == 32 {
+        let hints = reply.value::<u32>();
+        if hints.len() == 18 && hints[0] & P_WIN_GRAVITY != 0 {
+            return Ok(hints[17]);
+        }
+    }
+    Ok(xcb::GRAVITY_NORTH_WEST)
+}
+
+/// Get how far a frame is placed from where its client was, so the point
+/// of the client given by `gravity` stays where it is.
+///
+/// `border` and `titlebar` are the size of the frame's border and titlebar,
+/// and `client_border` is the border the client had before it was framed.
+fn get_gravity_offset(gravity: u32, border: u16, titlebar: u16, client_border: u16) -> (i16, i16) {
+    // The frame's border replaces the client's own, on each side.
+    let border = client_border as i16 - border as i16;
+    let titlebar = titlebar as i16;
+
+    let x = match gravity {
+        xcb::GRAVITY_NORTH | xcb::GRAVITY_CENTER | xcb::GRAVITY_SOUTH | xcb::GRAVITY_STATIC => {
+            border
+        }
+        xcb::GRAVITY_NORTH_EAST | xcb::GRAVITY_EAST | xcb::GRAVITY_SOUTH_EAST => border * 2,
+        _ => 0,
+    };
+    let y = match gravity {
+        xcb::GRAVITY_WEST | xcb::GRAVITY_CENTER | xcb::GRAVITY_EAST => border - titlebar / 2,
+        xcb::GRAVITY_SOUTH_WEST | xcb::GRAVITY_SOUTH | xcb::GRAVITY_SOUTH_EAST => {
+            border * 2 - titlebar
+        }
+        // The client window itself stays in place.
+        xcb::GRAVITY_STATIC => border - titlebar,
+        _ => 0,
+    };
+    (x, y)
+}
+
+/// Buttons drawn on the right side of a titlebar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TitlebarButton {
+    Close,
+    Maximize,
+}
+
+impl TitlebarButton {
+    /// All buttons, from right to left.
+    const ALL: [Self; 2] = [Self::Close, Self::Maximize];
+}
+
+/// Create a graphics context for drawing text on `drawable`, with the font
+/// and colors in `config`.
+///
+/// Also returns the baseline that vertically centers text in a bar `height`
+/// pixels tall. If the configured font can't be opened, the `fixed` font is
+/// used instead.
+pub fn create_text_gc(
+    conn: &xcb::Connection,
+    drawable: xcb::Drawable,
+    config: &TitlebarConfig,
+    height: u16,
+) -> NerdResult<(xcb::Gcontext, i16)> {
+    let font = conn.generate_id();
+    if let Err(e) = xcb::open_font_checked(conn, font, config.get_font()).request_check() {
+        warn!(
+            "Unable to open font {:?}, falling back to fixed: {}",
+            config.get_font(),
+            Error::from(e)
+        );
+        xcb::open_font_checked(conn, font, "fixed").request_check()?;
+    }
+    let info = match xcb::query_font(conn, font).get_reply() {
+        Ok(info) => info,
+        Err(e) => {
+            xcb::close_font(conn, font);
+            return Err(e.into());
+        }
+    };
+
+    let gc = conn.generate_id();
+    let created = xcb::create_gc_checked(
+        conn,
+        gc,
+        drawable,
+        &[
+            (xcb::GC_FOREGROUND, config.get_foreground()),
+            (xcb::GC_BACKGROUND, config.get_background()),
+            (xcb::GC_FONT, font),
+        ],
+    )
+    .request_check();
+    // The graphics context keeps its own reference to the font, if it was created.
+    xcb::close_font(conn, font);
+    created?;
+
+    let baseline = (height as i16 + info.font_ascent() - info.font_descent()) / 2;
+    Ok((gc, baseline))
+}
+
+/// Make a title drawable with a core font.
+///
+/// Titles are sent to the server as bytes, which core fonts decode one
+/// character per byte, so anything outside printable ASCII is replaced with
+/// `?`. The title is also cut off at 255 characters.
+pub fn sanitize_title(title: &str) -> String {
+    title
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && !c.is_ascii_control() {
+                c
+            } else {
+                '?'
+            }
+        })
+        .take(255)
+        .collect()
+}
+
+/// Titlebar drawn at the top of a client's frame, showing the client's title.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Titlebar {
+    window: xcb::Window,
+    /// Graphics context holding the font and colors used to draw the title.
+    gc: xcb::Gcontext,
+    height: u16,
+    /// Vertical position of the text baseline.
+    baseline: i16,
+    foreground: u32,
+    button_size: u16,
+    close_color: u32,
+    maximize_color: u32,
+}
+
+impl Titlebar {
+    /// Create a titlebar as a child of `frame`.
+    ///
+    /// If the configured font can't be opened, the `fixed` font is used instead.
+    pub fn create(
+        conn: &xcb::Connection,
+        frame: xcb::Window,
+        width: u16,
+        config: &TitlebarConfig,
+    ) -> NerdResult<Self> {
+        let window = conn.generate_id();
+        xcb::create_window_checked(
+            conn,
+            xcb::COPY_FROM_PARENT as u8,
+            window,
+            frame,
+            0,
+            0,
+            width.max(1),
+            config.get_height(),
+            0,
+            xcb::WINDOW_CLASS_INPUT_OUTPUT as u16,
+            xcb::COPY_FROM_PARENT,
+            &[
+                (xcb::CW_BACK_PIXEL, config.get_background()),
+                (
+                    xcb::CW_EVENT_MASK,
+                    xcb::EVENT_MASK_EXPOSURE
+                        | xcb::EVENT_MASK_BUTTON_PRESS
+                        | xcb::EVENT_MASK_BUTTON_RELEASE
+                        | xcb::EVENT_MASK_BUTTON_MOTION,
+                ),
+            ],
+        )
+        .request_check()?;
+        xcb::map_window_checked(conn, window).request_check()?;
+
+        let (gc, baseline) = create_text_gc(conn, window, config, config.get_height())?;
+        Ok(Self {
+            window,
+            gc,
+            height: config.get_height(),
+            baseline,
+            foreground: config.get_foreground(),
+            button_size: config.get_button_size(),
+            close_color: config.get_close_color(),
+            maximize_color: config.get_maximize_color(),
+        })
+    }
+
+    /// Get the titlebar window.
+    pub fn get_window(&self) -> xcb::Window {
+        self.window
+    }
+
+    /// Get the height of the titlebar in pixels.
+    pub fn get_height(&self) -> u16 {
+        self.height
+    }
+
+    /// Get the area covered by a button, for a titlebar `width` pixels wide.
+    fn get_button_rect(&self, width: u16, button: TitlebarButton) -> Rectangle {
+        let index = TitlebarButton::ALL
+            .iter()
+            .position(|b| b == &button)
+            .unwrap_or_default() as u16;
+        let padding = (self.height - self.button_size) / 2;
+        let offset = (index + 1) * (self.button_size + padding);
+
+        Rectangle::new(
+            width.saturating_sub(offset) as i16,
+            padding as i16,
+            self.button_size,
+            self.button_size,
+        )
+    }
+
+    /// Get the button at `(x, y)`, relative to the titlebar.
+    pub fn get_button_at(
+        &self,
+        conn: &xcb::Connection,
+        x: i16,
+        y: i16,
+    ) -> NerdResult<Option<TitlebarButton>> {
+        let width = xcb::get_geometry(conn, self.window).get_reply()?.width();
+
+        Ok(TitlebarButton::ALL
+            .into_iter()
+            .find(|b| self.get_button_rect(width, *b).contains(x, y)))
+    }
+
+    /// Clear the titlebar and draw `title` and the buttons on it.
+    ///
+    /// The title is passed through [`sanitize_title`] first.
+    pub fn draw(&self, conn: &xcb::Connection, title: &str) -> NerdResult<()> {
+        let title = sanitize_title(title);
+        xcb::clear_area_checked(conn, false, self.window, 0, 0, 0, 0).request_check()?;
+        xcb::image_text_8_checked(conn, self.window, self.gc, 4, self.baseline, &title)
+            .request_check()?;
+
+        if self.button_size == 0 {
+            return Ok(());
+        }
+
+        let width = xcb::get_geometry(conn, self.window).get_reply()?.width();
+        for button in TitlebarButton::ALL {
+            let color = match button {
+                TitlebarButton::Close => self.close_color,
+                TitlebarButton::Maximize => self.maximize_color,
+            };
+            xcb::change_gc_checked(conn, self.gc, &[(xcb::GC_FOREGROUND, color)])
+                .request_check()?;
+            xcb::poly_fill_rectangle_checked(
+                conn,
+                self.window,
+                self.gc,
+                &[self.get_button_rect(width, button).into()],
+            )
+            .request_check()?;
+        }
+        xcb::change_gc_checked(conn, self.gc, &[(xcb::GC_FOREGROUND, self.foreground)])
+            .request_check()?;
+        Ok(())
+    }
+
+    /// Free the resources held by the titlebar.
+    ///
+    /// The window itself is destroyed along with the frame.
+    fn free(&self, conn: &xcb::Connection) -> NerdResult<()> {
+        xcb::free_gc_checked(conn, self.gc).request_check()?;
+        Ok(())
+    }
+}
+
+/// Layer of the stacking order a client is kept in, from bottom to top.
+///
+/// Clients in a higher level are always stacked above clients in a lower one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum StackLevel {
+    Below,
+    #[default]
+    Normal,
+    Above,
+}
+
+/// A client window, along with the frame it is reparented into.
+///
+/// The frame is a direct child of the root window, and is what gets moved,
+/// resized, stacked and bordered. The client window fills the frame, below
+/// the titlebar if there is one.
+///
+/// Clients compare and hash by their window, so a client stays equal to
+/// itself when its border changes.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientWindow {
+    window: xcb::Window,
+    frame: xcb::Window,
+    titlebar: Option<Titlebar>,
+    border_width: u16,
+    /// Color shown in the frame where the client window doesn't cover it.
+    background: u32,
+    /// Border width the client had before it was framed.
+    original_border_width: u16,
+    /// The client's `win_gravity`, used to place the frame around it.
+    gravity: u32,
+    level: StackLevel,
+    /// Whether the client needs attention.
+    urgent: bool,
+    /// Opacity last set on the frame for compositors.
+    opacity: f32,
+}
+
+impl PartialEq for ClientWindow {
+    fn eq(&self, other: &Self) -> bool {
+        self.window == other.window
+    }
+}
+
+impl Eq for ClientWindow {}
+
+impl std::hash::Hash for ClientWindow {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.window.hash(state);
+    }
+}
+
+impl OwnsWindows for ClientWindow {
+    fn get_owned_windows(&self) -> Vec<xcb::Window> {
+        let mut windows = vec![self.window, self.frame];
+        windows.extend(self.titlebar.map(|t| t.get_window()));
+        windows
+    }
+}
+
+impl ClientWindow {
+    /// Create a frame for a window and reparent the window into it.
+    ///
+    /// The frame is placed where the window currently is, and is not mapped.
+    /// The window is added to the save set, so it is restored to the root
+    /// window if the window manager exits unexpectedly.
+    pub fn from_window(
+        conn: &xcb::Connection,
+        window: xcb::Window,
+        border: &BorderConfig,
+        background: u32,
+        titlebar: &TitlebarConfig,
+    ) -> NerdResult<Self> {
+        let properties = WindowProperties::query(conn, window)?;
+        let (width, height) = properties.get_size();
+        let titlebar_height = if titlebar.is_enabled() {
+            titlebar.get_height()
+        } else {
+            0
+        };
+
+        let gravity = get_win_gravity(conn, window)?;
+        let (dx, dy) = get_gravity_offset(
+            gravity,
+            border.get_width(),
+            titlebar_height,
+            properties.get_border_width(),
+        );
+        let (x, y) = properties.get_position();
+        let (x, y) = (x + dx, y + dy);
+
+        let frame = conn.generate_id();
+        xcb::create_window_checked(
+            conn,
+            xcb::COPY_FROM_PARENT as u8,
+            frame,
+            properties.get_root(),
+            x,
+            y,
+            width,
+            height.saturating_add(titlebar_height),
+            border.get_width(),
+            xcb::WINDOW_CLASS_INPUT_OUTPUT as u16,
+            xcb::COPY_FROM_PARENT,
+            &[
+                (xcb::CW_BACK_PIXEL, background),
+                (xcb::CW_BORDER_PIXEL, border.get_color()),
+                (
+                    xcb::CW_EVENT_MASK,
+                    xcb::EVENT_MASK_SUBSTRUCTURE_REDIRECT | xcb::EVENT_MASK_SUBSTRUCTURE_NOTIFY,
+                ),
+            ],
+        )
+        .request_check()?;
+
+        let titlebar = if titlebar.is_enabled() {
+            match Titlebar::create(conn, frame, width, titlebar) {
+                Ok(titlebar) => Some(titlebar),
+                Err(e) => {
+                    xcb::destroy_window(conn, frame);
+                    return Err(e);
+                }
+            }
+        } else {
+            None
+        };
+
+        let client = Self {
+            window,
+            frame,
+            titlebar,
+            border_width: border.get_width(),
+            background,
+            original_border_width: properties.get_border_width(),
+            gravity,
+            level: StackLevel::Normal,
+            urgent: false,
+            opacity: 1.0,
+        };
+
+        // Reparenting doesn't tell the client where it ended up on the screen.
+        let inner = border.get_width() as i16;
+        let geometry = Rectangle::new(x + inner, y + inner + titlebar_height as i16, width, height);
+        if let Err(e) = client.reparent_into_frame(conn, geometry) {
+            let (x, y) = properties.get_position();
+            client.discard(conn, properties.get_root(), x, y);
+            return Err(e);
+        }
+        trace!("Framed window {} in {}", window, frame);
+        Ok(client)
+    }
+
+    /// Reparent the client window into its frame, and tell it its new
+    /// `geometry` on the screen.
+    fn reparent_into_frame(&self, conn: &xcb::Connection, geometry: Rectangle) -> NerdResult<()> {
+        // Watch for title changes.
+        xcb::change_window_attributes_checked(
+            conn,
+            self.window,
+            &[(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_PROPERTY_CHANGE)],
+        )
+        .request_check()?;
+
+        // The frame draws the border, so the client doesn't need its own.
+        xcb::configure_window_checked(
+            conn,
+            self.window,
+            &[(xcb::CONFIG_WINDOW_BORDER_WIDTH as u16, 0)],
+        )
+        .request_check()?;
+
+        xcb::change_save_set_checked(conn, xcb::SET_MODE_INSERT as u8, self.window)
+            .request_check()?;
+        xcb::reparent_window_checked(
+            conn,
+            self.window,
+            self.frame,
+            0,
+            self.titlebar_height() as i16,
+        )
+        .request_check()?;
+
+        self.send_configure_notify(conn, geometry)
+    }
+
+    /// Undo a partly done [`ClientWindow::from_window`], putting the client
+    /// window back on `root` at `x`, `y` and destroying the frame.
+    ///
+    /// Errors are ignored, as the client window may already be gone.
+    fn discard(&self, conn: &xcb::Connection, root: xcb::Window, x: i16, y: i16) {
+        // The client window must leave the frame, or it is destroyed along with it.
+        xcb::reparent_window(conn, self.window, root, x, y);
+        xcb::configure_window(
+            conn,
+            self.window,
+            &[(
+                xcb::CONFIG_WINDOW_BORDER_WIDTH as u16,
+                self.original_border_width as u32,
+            )],
+        );
+        xcb::change_save_set(conn, xcb::SET_MODE_DELETE as u8, self.window);
+        if let Some(titlebar) = &self.titlebar {
+            xcb::free_gc(conn, titlebar.gc);
+        }
+        xcb::destroy_window(conn, self.frame);
+        conn.flush();
+    }
+
+    /// Get the client window.
+    pub fn get_window(&self) -> xcb::Window {
+        self.window
+    }
+
+    /// Get the frame the client window is reparented into.
+    pub fn get_frame(&self) -> xcb::Window {
+        self.frame
+    }
+
+    /// Get the titlebar drawn on the frame, if any.
+    pub fn get_titlebar(&self) -> Option<&Titlebar> {
+        self.titlebar.as_ref()
+    }
+
+    /// Get the level of the stacking order the client is kept in.
+    pub fn get_level(&self) -> StackLevel {
+        self.level
+    }
+
+    /// Set the level of the stacking order the client is kept in.
+    ///
+    /// This doesn't restack the client.
+    pub fn set_level(&mut self, level: StackLevel) {
+        self.level = level;
+    }
+
+    /// Check if the client needs attention.
+    pub fn is_urgent(&self) -> bool {
+        self.urgent
+    }
+
+    /// Set whether the client needs attention.
+    ///
+    /// This doesn't change the client's border.
+    pub fn set_urgent(&mut self, urgent: bool) {
+        self.urgent = urgent;
+    }
+
+    /// Get the opacity last set on the frame, `1.0` if it never was.
+    pub fn get_opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    /// Record the opacity set on the frame.
+    ///
+    /// This doesn't change the frame's `_NET_WM_WINDOW_OPACITY`.
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
+    }
+
+    /// Check if `window` is this client's titlebar.
+    pub fn is_titlebar(&self, window: xcb::Window) -> bool {
+        self.titlebar.is_some_and(|t| t.get_window() == window)
+    }
+
+    /// Get the size of the frame on the left, right, top and bottom of the
+    /// client window, for `_NET_FRAME_EXTENTS`.
+    pub fn get_frame_extents(&self) -> (u32, u32, u32, u32) {
+        let border = self.border_width as u32;
+        (
+            border,
+            border,
+            border + self.titlebar_height() as u32,
+            border,
+        )
+    }
+
+    /// Get the height of the titlebar, or `0` if there is none.
+    fn titlebar_height(&self) -> u16 {
+        self.titlebar.map_or(0, |t| t.get_height())
+    }
+
+    /// Map the client window and its frame.
+    pub fn map(&self, conn: &xcb::Connection) -> NerdResult<()> {
+        xcb::map_window_checked(conn, self.window).request_check()?;
+        xcb::map_window_checked(conn, self.frame).request_check()?;
+        Ok(())
+    }
+
+    /// Unmap the frame, hiding the client window along with it.
+    pub fn unmap(&self, conn: &xcb::Connection) -> NerdResult<()> {
+        xcb::unmap_window_checked(conn, self.frame).request_check()?;
+        Ok(())
+    }
+
+    /// Raise the frame to the top of the stack.
+    pub fn raise(&self, conn: &xcb::Connection) -> NerdResult<()> {
+        xcb::configure_window_checked(
+            conn,
+            self.frame,
+            &[(xcb::CONFIG_WINDOW_STACK_MODE as u16, xcb::STACK_MODE_ABOVE)],
+        )
+        .request_check()?;
+        Ok(())
+    }
+
+    /// Lower the client below all its siblings.
+    pub fn lower(&self, conn: &xcb::Connection) -> NerdResult<()> {
+        xcb::configure_window_checked(
+            conn,
+            self.frame,
+            &[(xcb::CONFIG_WINDOW_STACK_MODE as u16, xcb::STACK_MODE_BELOW)],
+        )
+        .request_check()?;
+        Ok(())
+    }
+
+    /// Change the width and color of the frame's border.
+    ///
+    /// Returns `true` if the width changed, which changes the frame extents.
+    pub fn set_border(
+        &mut self,
+        conn: &xcb::Connection,
+        border: &BorderConfig,
+    ) -> NerdResult<bool> {
+        xcb::change_window_attributes_checked(
+            conn,
+            self.frame,
+            &[(xcb::CW_BORDER_PIXEL, border.get_color())],
+        )
+        .request_check()?;
+
+        if border.get_width() != self.border_width {
+            xcb::configure_window_checked(
+                conn,
+                self.frame,
+                &[(
+                    xcb::CONFIG_WINDOW_BORDER_WIDTH as u16,
+                    border.get_width() as u32,
+                )],
+            )
+            .request_check()?;
+            self.border_width = border.get_width();
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Change the color shown in the frame where the client window doesn't cover it.
+    pub fn set_background(&mut self, conn: &xcb::Connection, background: u32) -> NerdResult<()> {
+        if background == self.background {
+            return Ok(());
+        }
+
+        xcb::change_window_attributes_checked(
+            conn,
+            self.frame,
+            &[(xcb::CW_BACK_PIXEL, background)],
+        )
+        .request_check()?;
+        // The new background is only drawn once the frame is repainted.
+        xcb::clear_area_checked(conn, false, self.frame, 0, 0, 0, 0).request_check()?;
+        self.background = background;
+        Ok(())
+    }
+
+    /// Get the geometry of the client, including its frame's border and titlebar.
+    pub fn get_geometry(&self, conn: &xcb::Connection) -> NerdResult<Rectangle> {
+        Ok(WindowProperties::query(conn, self.frame)?.get_outer_geometry())
+    }
+
+    /// Move and resize the client.
+    ///
+    /// The geometry includes the frame's border and titlebar, so the client
+    /// window will be smaller than `geometry`.
+    pub fn configure(&self, conn: &xcb::Connection, geometry: Rectangle) -> NerdResult<()> {
+        let (cookies, inner) = self.configure_windows(conn, geometry, true);
+        for cookie in cookies {
+            cookie.request_check()?;
+        }
+
+        self.send_configure_notify(conn, inner)
+    }
+
+    /// Move and resize the client, without waiting for the server.
+    ///
+    /// This is like [`ClientWindow::configure`], but errors are reported as
+    /// events instead of being returned. Requests are only sent when the
+    /// connection is flushed, so many clients can be configured in one batch.
+    pub fn configure_unchecked(&self, conn: &xcb::Connection, geometry: Rectangle) {
+        let (_, inner) = self.configure_windows(conn, geometry, false);
+        self.request_configure_notify(conn, inner, false);
+    }
+
+    /// Move and resize the client while it is being dragged or animated.
+    ///
+    /// Unlike [`ClientWindow::configure`], this doesn't wait for the server or
+    /// notify the client, so it must be followed by a call to `configure`
+    /// once the drag or animation ends.
+    pub fn drag_configure(&self, conn: &xcb::Connection, geometry: Rectangle) {
+        self.configure_windows(conn, geometry, false);
+        conn.flush();
+    }
+
+    /// Send the requests to move and resize the frame, titlebar and client window.
+    ///
+    /// Returns the cookies of the requests, which can only be checked if
+    /// `checked` is set, along with the geometry of the client window
+    /// relative to the root window.
+    fn configure_windows<'a>(
+        &self,
+        conn: &'a xcb::Connection,
+        geometry: Rectangle,
+        checked: bool,
+    ) -> (Vec<xcb::VoidCookie<'a>>, Rectangle) {
+        let Rectangle {
+            x,
+            y,
+            width,
+            height,
+        } = geometry;
+        let configure = |window, values: &[(u16, u32)]| {
+            if checked {
+                xcb::configure_window_checked(conn, window, values)
+            } else {
+                xcb::configure_window(conn, window, values)
+            }
+        };
+
+        let inner_width = width.saturating_sub(self.border_width * 2).max(1) as u32;
+        let inner_height = height.saturating_sub(self.border_width * 2).max(1) as u32;
+
+        let mut cookies = vec![configure(
+            self.frame,
+            &[
+                (xcb::CONFIG_WINDOW_X as u16, x as u32),
+                (xcb::CONFIG_WINDOW_Y as u16, y as u32),
+                (xcb::CONFIG_WINDOW_WIDTH as u16, inner_width),
+                (xcb::CONFIG_WINDOW_HEIGHT as u16, inner_height),
+            ],
+        )];
+
+        if let Some(titlebar) = &self.titlebar {
+            cookies.push(configure(
+                titlebar.get_window(),
+                &[(xcb::CONFIG_WINDOW_WIDTH as u16, inner_width)],
+            ));
+        }
+
+        let titlebar_height = self.titlebar_height() as u32;
+        let client_height = inner_height.saturating_sub(titlebar_height).max(1);
+        cookies.push(configure(
+            self.window,
+            &[
+                (xcb::CONFIG_WINDOW_WIDTH as u16, inner_width),
+                (xcb::CONFIG_WINDOW_HEIGHT as u16, client_height),
+            ],
+        ));
+
+        let inner = Rectangle::new(
+            x + self.border_width as i16,
+            y + (self.border_width + self.titlebar_height()) as i16,
+            inner_width as u16,
+            client_height as u16,
+        );
+        (cookies, inner)
+    }
+
+    /// Move the client without resizing it.
+    ///
+    /// `x` and `y` are the position of the outer corner of the frame's border.
+    pub fn move_to(&self, conn: &xcb::Connection, x: i16, y: i16) -> NerdResult<()> {
+        xcb::configure_window_checked(
+            conn,
+            self.frame,
+            &[
+                (xcb::CONFIG_WINDOW_X as u16, x as u32),
+                (xcb::CONFIG_WINDOW_Y as u16, y as u32),
+            ],
+        )
+        .request_check()?;
+
+        let geometry = xcb::get_geometry(conn, self.window).get_reply()?;
+        self.send_configure_notify(
+            conn,
+            Rectangle::new(
+                x + self.border_width as i16,
+                y + (self.border_width + self.titlebar_height()) as i16,
+                geometry.width(),
+                geometry.height(),
+            ),
+        )
+    }
+
+    /// Move the client to the center of `area`, keeping its current size.
+    ///
+    /// Clients larger than `area` are aligned to its top left corner instead.
+    pub fn center_on(&self, conn: &xcb::Connection, area: Rectangle) -> NerdResult<()> {
+        let geometry = self.get_geometry(conn)?;
+        let centered = area.center_size(geometry.width, geometry.height);
+        self.move_to(conn, centered.x, centered.y)
+    }
+
+    /// Move the client while it is being dragged.
+    ///
+    /// Unlike [`ClientWindow::move_to`], this doesn't wait for the server or
+    /// notify the client, so it must be followed by a call to `move_to`
+    /// once the drag ends.
+    pub fn drag_to(&self, conn: &xcb::Connection, x: i16, y: i16) {
+        xcb::configure_window(
+            conn,
+            self.frame,
+            &[
+                (xcb::CONFIG_WINDOW_X as u16, x as u32),
+                (xcb::CONFIG_WINDOW_Y as u16, y as u32),
+            ],
+        );
+        conn.flush();
+    }
+
+    /// Tell the client where it is on screen.
+    ///
+    /// The client only receives real `ConfigureNotify` events relative to its
+    /// frame, so the ICCCM requires a synthetic one with root coordinates
+    /// whenever the frame is moved or resized.
+    ///
+    /// `geometry` is that of the client window, relative to the root window.
+    pub fn send_configure_notify(
+        &self,
+        conn: &xcb::Connection,
+        geometry: Rectangle,
+    ) -> NerdResult<()> {
+        self.request_configure_notify(conn, geometry, true)
+            .request_check()?;
+        Ok(())
+    }
+
+    /// Tell the client where it is on screen, without moving it.
+    ///
+    /// This answers configure requests the window manager doesn't grant.
+    pub fn notify_geometry(&self, conn: &xcb::Connection) -> NerdResult<()> {
+        let outer = self.get_geometry(conn)?;
+        let (left, right, top, bottom) = self.get_frame_extents();
+        let inner = Rectangle::new(
+            outer.x + left as i16,
+            outer.y + top as i16,
+            outer.width.saturating_sub((left + right) as u16).max(1),
+            outer.height.saturating_sub((top + bottom) as u16).max(1),
+        );
+        self.send_configure_notify(conn, inner)
+    }
+
+    /// Send a synthetic `ConfigureNotify` to the client.
+    ///
+    /// The returned cookie can only be checked if `checked` is set.
+    fn request_configure_notify<'a>(
+        &self,
+        conn: &'a xcb::Connection,
+        geometry: Rectangle,
+        checked: bool,
+    ) -> xcb::VoidCookie<'a> {
+        let event = xcb::ConfigureNotifyEvent::new(
+            self.window,
+            self.window,
+            xcb::NONE,
+            geometry.x,
+            geometry.y,
+            geometry.width,
+            geometry.height,
+            0,
+            false,
+        );
+        let mask = xcb::EVENT_MASK_STRUCTURE_NOTIFY;
+        if checked {
+            xcb::send_event_checked(conn, false, self.window, mask, &event)
+        } else {
+            xcb::send_event(conn, false, self.window, mask, &event)
+        }
+    }
+
+    /// Move the pointer to the center of the client.
+    pub fn warp_pointer(&self, conn: &xcb::Connection) -> NerdResult<()> {
+        let properties = WindowProperties::query(conn, self.frame)?;
+        let (x, y) = properties.get_outer_geometry().center();
+        xcb::warp_pointer_checked(conn, xcb::NONE, properties.get_root(), 0, 0, 0, 0, x, y)
+            .request_check()?;
+        Ok(())
+    }
+
+    /// Give the client input focus.
+    pub fn set_input_focus(&self, conn: &xcb::Connection) -> NerdResult<()> {
+        xcb::set_input_focus_checked(
+            conn,
+            xcb::INPUT_FOCUS_POINTER_ROOT as u8,
+            self.window,
+            xcb::CURRENT_TIME,
+        )
+        .request_check()?;
+        Ok(())
+    }
+
+    /// Stop managing the client, and destroy its frame.
+    ///
+    /// If the client window still exists, it is reparented back to the root
+    /// window with its original border, and removed from the save set. Pass
+    /// `destroyed` if the client window has already been destroyed.
+    pub fn release(&self, conn: &xcb::Connection, destroyed: bool) -> NerdResult<()> {
+        if !destroyed {
+            let geometry = xcb::get_geometry(conn, self.frame).get_reply()?;
+            // Undo the offset the frame was placed with.
+            let (dx, dy) = get_gravity_offset(
+                self.gravity,
+                self.border_width,
+                self.titlebar_height(),
+                self.original_border_width,
+            );
+
+            xcb::reparent_window_checked(
+                conn,
+                self.window,
+                geometry.root(),
+                geometry.x() - dx,
+                geometry.y() - dy,
+            )
+            .request_check()?;
+            xcb::configure_window_checked(
+                conn,
+                self.window,
+                &[(
+                    xcb::CONFIG_WINDOW_BORDER_WIDTH as u16,
+                    self.original_border_width as u32,
+                )],
+            )
+            .request_check()?;
+            xcb::change_save_set_checked(conn, xcb::SET_MODE_DELETE as u8, self.window)
+                .request_check()?;
+        }
+
+        if let Some(titlebar) = &self.titlebar {
+            titlebar.free(conn)?;
+        }
+        xcb::destroy_window_checked(conn, self.frame).request_check()?;
+        trace!("Released window {} from {}", self.window, self.frame);
+        Ok(())
+    }
+}
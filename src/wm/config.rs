@@ -4,21 +4,63 @@
 
 use serde::{Deserialize, Serialize};
 
-use super::actions;
-use crate::events::input;
+use super::{actions, layout};
+use crate::events::{input, keyconvert};
+use crate::prelude::*;
+
+/// Environment variable that overrides the configured log level, such as
+/// `NERDWM_LOG=debug`.
+pub const LOG_LEVEL_VAR: &str = "NERDWM_LOG";
+
+/// Get the log level used when neither the environment nor the config sets
+/// one: `trace` for debug builds, and `info` otherwise.
+pub fn get_default_log_level() -> log::LevelFilter {
+    if cfg!(debug_assertions) {
+        log::LevelFilter::Trace
+    } else {
+        log::LevelFilter::Info
+    }
+}
+
+/// Get the log level set with [`LOG_LEVEL_VAR`], if it is set to a valid level.
+pub fn get_env_log_level() -> Option<log::LevelFilter> {
+    std::env::var(LOG_LEVEL_VAR).ok()?.parse().ok()
+}
 
 /// Keyboard binding, consisting of a regular key press and an
 /// optional modifier mask.
-#[derive(Deserialize, Serialize)]
+///
+/// A binding may also be a sequence, where the first key acts as a prefix
+/// and the keys in `then` must be pressed after it, in order.
+#[derive(Deserialize, Serialize, Debug)]
 pub struct KeyBind {
     keysym: input::Key,
+    #[serde(default)]
     modifiers: Vec<input::ModMask>,
+    /// Keys that must follow this one. Sequences of the keys
+    /// listed here are ignored.
+    #[serde(default)]
+    then: Vec<KeyBind>,
 }
 
 impl KeyBind {
     #[must_use]
     pub fn new(keysym: input::Key, modifiers: Vec<input::ModMask>) -> Self {
-        Self { keysym, modifiers }
+        Self {
+            keysym,
+            modifiers,
+            then: vec![],
+        }
+    }
+
+    /// Get the keys that must be pressed after this one.
+    pub fn get_sequence(&self) -> &[KeyBind] {
+        &self.then
+    }
+
+    /// Check if a key press with the given symbol and modifier state matches this key.
+    pub fn matches(&self, keysym: xcb::Keysym, state: u32) -> bool {
+        self.get_modifier_mask() == state && self.keysym as u32 == keysym
     }
 
     /// Get the key symbol associated with this binding.
@@ -34,11 +76,22 @@ impl KeyBind {
         }
         mask
     }
+
+    /// Replace modifiers named after keys, such as `Super`, with the
+    /// modifiers they are mapped to, including in the rest of the sequence.
+    fn resolve_modifiers(&mut self, map: &input::ModifierMap) {
+        for modifier in &mut self.modifiers {
+            *modifier = modifier.resolve(map);
+        }
+        for next in &mut self.then {
+            next.resolve_modifiers(map);
+        }
+    }
 }
 
 /// Mouse button binding, consisting of a regular mouse button press
 /// and an optional modifier mask.
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct MouseBind {
     button: input::Button,
     modifiers: Vec<input::ModMask>,
@@ -63,14 +116,51 @@ impl MouseBind {
         }
         mask
     }
+
+    /// Replace modifiers named after keys, such as `Super`, with the
+    /// modifiers they are mapped to.
+    fn resolve_modifiers(&mut self, map: &input::ModifierMap) {
+        for modifier in &mut self.modifiers {
+            *modifier = modifier.resolve(map);
+        }
+    }
+}
+
+/// Situation in which the bindings of an action apply.
+///
+/// While a window is being moved or resized with the pointer, only bindings
+/// with [`ActionContext::MoveMode`] apply.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ActionContext {
+    /// Whatever is focused.
+    #[default]
+    Always,
+    /// While the focused window is floating.
+    Floating,
+    /// While the focused window is tiled, or no window is focused.
+    Tiling,
+    /// While a window is being moved or resized. Only key bindings apply then.
+    MoveMode,
 }
 
 /// Configuration for bindings related to window manager actions.
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct ActionConfig {
     action: actions::ActionType,
     keybind: Option<KeyBind>,
     mousebind: Option<MouseBind>,
+    /// Modifier key that triggers the action when pressed and released
+    /// on its own, such as `XK_Super_L`.
+    ///
+    /// The key is grabbed, so the keyboard stays grabbed while it is held.
+    /// Key bindings still work then, but other keys pressed along with it
+    /// are not passed on to applications.
+    tapbind: Option<KeyBind>,
+    /// Command to run, for [`actions::ActionType::Spawn`].
+    command: Option<String>,
+    /// When the bindings apply.
+    #[serde(default)]
+    context: ActionContext,
 }
 
 impl ActionConfig {
@@ -79,11 +169,17 @@ impl ActionConfig {
         action: actions::ActionType,
         keybind: Option<KeyBind>,
         mousebind: Option<MouseBind>,
+        tapbind: Option<KeyBind>,
+        command: Option<String>,
+        context: ActionContext,
     ) -> Self {
         Self {
             action,
             keybind,
             mousebind,
+            tapbind,
+            command,
+            context,
         }
     }
 
@@ -100,22 +196,655 @@ impl ActionConfig {
     pub fn get_mousebind(&self) -> &Option<MouseBind> {
         &self.mousebind
     }
+
+    /// Get the modifier tap binding associated with this action.
+    pub fn get_tapbind(&self) -> &Option<KeyBind> {
+        &self.tapbind
+    }
+
+    /// Get the command associated with this action.
+    pub fn get_command(&self) -> &Option<String> {
+        &self.command
+    }
+
+    /// Get the situation in which the bindings of this action apply.
+    pub fn get_context(&self) -> ActionContext {
+        self.context
+    }
+}
+
+/// Border drawn around a client's frame.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+pub struct BorderConfig {
+    width: u16,
+    color: u32,
+}
+
+impl BorderConfig {
+    #[must_use]
+    pub fn new(width: u16, color: u32) -> Self {
+        Self { width, color }
+    }
+
+    /// Get the width of the border in pixels.
+    pub fn get_width(&self) -> u16 {
+        self.width
+    }
+
+    /// Get the color of the border, as `0xRRGGBB`.
+    pub fn get_color(&self) -> u32 {
+        self.color
+    }
+}
+
+/// Titlebar drawn at the top of a client's frame.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct TitlebarConfig {
+    /// Height in pixels. Titlebars are disabled if this is `0`.
+    height: u16,
+    /// Name of the core X font used to draw the title.
+    font: String,
+    background: u32,
+    foreground: u32,
+    /// Size of the close and maximize buttons, in pixels.
+    button_size: u16,
+    close_color: u32,
+    maximize_color: u32,
+}
+
+impl Default for TitlebarConfig {
+    fn default() -> Self {
+        Self {
+            height: 18,
+            font: "fixed".to_owned(),
+            background: 0x0022_2222,
+            foreground: 0x00cc_cccc,
+            button_size: 12,
+            close_color: 0x00cc_4444,
+            maximize_color: 0x0044_aa44,
+        }
+    }
+}
+
+impl TitlebarConfig {
+    #[must_use]
+    pub fn new(height: u16, font: String, background: u32, foreground: u32) -> Self {
+        Self {
+            height,
+            font,
+            background,
+            foreground,
+            ..Default::default()
+        }
+    }
+
+    /// Get the height of the titlebar in pixels.
+    pub fn get_height(&self) -> u16 {
+        self.height
+    }
+
+    /// Check if titlebars should be drawn at all.
+    pub fn is_enabled(&self) -> bool {
+        self.height > 0
+    }
+
+    /// Get the name of the font used to draw the title.
+    pub fn get_font(&self) -> &str {
+        &self.font
+    }
+
+    /// Get the background color of the titlebar, as `0xRRGGBB`.
+    pub fn get_background(&self) -> u32 {
+        self.background
+    }
+
+    /// Get the color of the title text, as `0xRRGGBB`.
+    pub fn get_foreground(&self) -> u32 {
+        self.foreground
+    }
+
+    /// Get the size of the titlebar buttons in pixels.
+    ///
+    /// Buttons never exceed the height of the titlebar.
+    pub fn get_button_size(&self) -> u16 {
+        self.button_size.min(self.height)
+    }
+
+    /// Get the color of the close button, as `0xRRGGBB`.
+    pub fn get_close_color(&self) -> u32 {
+        self.close_color
+    }
+
+    /// Get the color of the maximize button, as `0xRRGGBB`.
+    pub fn get_maximize_color(&self) -> u32 {
+        self.maximize_color
+    }
+}
+
+/// Configuration for the appearance of clients.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default)]
+pub struct LayoutConfig {
+    border_focused: BorderConfig,
+    border_unfocused: BorderConfig,
+    /// Border drawn around clients that need attention, until they are focused.
+    border_urgent: BorderConfig,
+    titlebar: TitlebarConfig,
+    /// Color of the focused client's frame, shown where the client doesn't cover it.
+    frame_background: u32,
+    /// Color of all other frames. Defaults to `frame_background`.
+    frame_background_unfocused: Option<u32>,
+    /// Opacity of the focused client, from 0 to 1. Needs a compositor.
+    focused_opacity: f32,
+    /// Opacity of all other clients, from 0 to 1. Needs a compositor.
+    unfocused_opacity: f32,
+    /// Space between tiled clients, in pixels.
+    gap_size: u16,
+    /// Space between tiled clients and the edges of the tiling area, in pixels.
+    outer_gap: u16,
+    /// Whether moving a client past either end of the stack wraps around to the other end.
+    wrap_stack: bool,
+    /// Fraction of the available area the master area grows or shrinks by.
+    master_ratio_step: f32,
+    /// Whether the pointer follows focus changes made with the keyboard.
+    warp_pointer_on_focus: bool,
+    /// Whether newly mapped clients take the focus. Otherwise they are marked
+    /// as needing attention.
+    focus_new_windows: bool,
+    /// Distance in pixels within which windows moved with the pointer snap to
+    /// the edges of the desktop and other windows. `0` disables snapping.
+    snap_threshold: u16,
+    /// Whether resizing with the pointer always drags the corner nearest to
+    /// where it started, instead of also dragging edges.
+    resize_from_corners: bool,
+    /// Whether tiled clients slide to their new place when the layout changes.
+    animations: bool,
+    /// How long tiled clients take to reach their new place, in milliseconds.
+    animation_duration: u64,
+    /// How long clients asked to close get before they are killed, in milliseconds.
+    close_timeout: u64,
+    /// Names of the layouts every desktop cycles through, in order.
+    layouts: Vec<String>,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            border_focused: BorderConfig::new(2, 0x0000_5577),
+            border_unfocused: BorderConfig::new(2, 0x0044_4444),
+            border_urgent: BorderConfig::new(2, 0x00cc_4444),
+            titlebar: TitlebarConfig::default(),
+            frame_background: 0x0011_1111,
+            frame_background_unfocused: None,
+            focused_opacity: 1.0,
+            unfocused_opacity: 1.0,
+            gap_size: 0,
+            outer_gap: 0,
+            wrap_stack: true,
+            master_ratio_step: 0.05,
+            warp_pointer_on_focus: false,
+            focus_new_windows: true,
+            snap_threshold: 10,
+            resize_from_corners: false,
+            animations: false,
+            animation_duration: 150,
+            close_timeout: 5000,
+            layouts: layout::DEFAULT_NAMES
+                .iter()
+                .map(|n| (*n).to_owned())
+                .collect(),
+        }
+    }
+}
+
+impl LayoutConfig {
+    /// Get the border drawn around the focused client.
+    pub fn get_border_focused(&self) -> &BorderConfig {
+        &self.border_focused
+    }
+
+    /// Get the border drawn around all other clients.
+    pub fn get_border_unfocused(&self) -> &BorderConfig {
+        &self.border_unfocused
+    }
+
+    /// Get the border drawn around clients that need attention.
+    pub fn get_border_urgent(&self) -> &BorderConfig {
+        &self.border_urgent
+    }
+
+    /// Get the titlebar drawn on top of clients.
+    pub fn get_titlebar(&self) -> &TitlebarConfig {
+        &self.titlebar
+    }
+
+    /// Get the color of the focused client's frame.
+    pub fn get_frame_background(&self) -> u32 {
+        self.frame_background
+    }
+
+    /// Get the color of the frames of all other clients.
+    pub fn get_frame_background_unfocused(&self) -> u32 {
+        self.frame_background_unfocused
+            .unwrap_or(self.frame_background)
+    }
+
+    /// Get the opacity of the focused client.
+    pub fn get_focused_opacity(&self) -> f32 {
+        self.focused_opacity
+    }
+
+    /// Get the opacity of all other clients.
+    pub fn get_unfocused_opacity(&self) -> f32 {
+        self.unfocused_opacity
+    }
+
+    /// Get the space between tiled clients.
+    pub fn get_gap_size(&self) -> u16 {
+        self.gap_size
+    }
+
+    /// Get the space between tiled clients and the edges of the tiling area.
+    pub fn get_outer_gap(&self) -> u16 {
+        self.outer_gap
+    }
+
+    /// Check if moving a client past either end of the stack wraps around.
+    pub fn get_wrap_stack(&self) -> bool {
+        self.wrap_stack
+    }
+
+    /// Get the fraction of the available area the master area grows or shrinks by.
+    pub fn get_master_ratio_step(&self) -> f32 {
+        self.master_ratio_step
+    }
+
+    /// Check if the pointer should be moved to clients focused with the keyboard.
+    pub fn get_warp_pointer_on_focus(&self) -> bool {
+        self.warp_pointer_on_focus
+    }
+
+    /// Check if newly mapped clients should take the focus.
+    pub fn get_focus_new_windows(&self) -> bool {
+        self.focus_new_windows
+    }
+
+    /// Get the distance within which moved windows snap to nearby edges.
+    pub fn get_snap_threshold(&self) -> u16 {
+        self.snap_threshold
+    }
+
+    /// Check if resizing with the pointer only drags corners.
+    pub fn get_resize_from_corners(&self) -> bool {
+        self.resize_from_corners
+    }
+
+    /// Check if tiled clients are animated when the layout changes.
+    pub fn get_animations(&self) -> bool {
+        self.animations
+    }
+
+    /// Get how long tiled clients take to reach their new place.
+    pub fn get_animation_duration(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.animation_duration)
+    }
+
+    /// Get how long clients asked to close get before they are killed.
+    pub fn get_close_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.close_timeout)
+    }
+
+    /// Get the names of the layouts every desktop cycles through.
+    pub fn get_layouts(&self) -> &Vec<String> {
+        &self.layouts
+    }
+}
+
+/// Rule applied to windows whose `WM_CLASS` matches when they are mapped.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Rule {
+    match_class: String,
+    #[serde(default)]
+    floating: bool,
+    desktop: Option<usize>,
+}
+
+impl Rule {
+    #[must_use]
+    pub fn new(match_class: String, floating: bool, desktop: Option<usize>) -> Self {
+        Self {
+            match_class,
+            floating,
+            desktop,
+        }
+    }
+
+    /// Check if this rule applies to a window with the given instance and class names.
+    pub fn matches(&self, instance: &str, class: &str) -> bool {
+        self.match_class == class || self.match_class == instance
+    }
+
+    /// Whether matching windows should be kept out of the layout.
+    pub fn is_floating(&self) -> bool {
+        self.floating
+    }
+
+    /// Index of the desktop matching windows should be placed on.
+    pub fn get_desktop(&self) -> Option<usize> {
+        self.desktop
+    }
+}
+
+/// A desktop created when the window manager starts.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DesktopConfig {
+    name: String,
+    /// Name of the layout the desktop starts with.
+    layout: Option<String>,
+    /// Index of the monitor showing the desktop.
+    #[serde(default)]
+    monitor: usize,
+}
+
+impl DesktopConfig {
+    #[must_use]
+    pub fn new(name: String, layout: Option<String>, monitor: usize) -> Self {
+        Self {
+            name,
+            layout,
+            monitor,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the name of the layout the desktop starts with, if it isn't the first one.
+    pub fn get_layout(&self) -> Option<&str> {
+        self.layout.as_deref()
+    }
+
+    /// Get the index of the monitor showing the desktop.
+    pub fn get_monitor(&self) -> usize {
+        self.monitor
+    }
+}
+
+/// Window shown and hidden by [`actions::ActionType::ToggleScratchpad`].
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ScratchpadConfig {
+    /// Command run to create the window, the first time the scratchpad is shown.
+    command: String,
+    /// Instance or class name identifying the window in its `WM_CLASS`.
+    match_class: String,
+    /// Width, as a fraction of the work area.
+    #[serde(default = "ScratchpadConfig::default_size")]
+    width: f32,
+    /// Height, as a fraction of the work area.
+    #[serde(default = "ScratchpadConfig::default_size")]
+    height: f32,
+}
+
+impl ScratchpadConfig {
+    #[must_use]
+    pub fn new(command: String, match_class: String, width: f32, height: f32) -> Self {
+        Self {
+            command,
+            match_class,
+            width,
+            height,
+        }
+    }
+
+    fn default_size() -> f32 {
+        0.6
+    }
+
+    /// Get the command that creates the scratchpad window.
+    pub fn get_command(&self) -> &str {
+        &self.command
+    }
+
+    /// Check if a window with the given instance and class names is the scratchpad.
+    pub fn matches(&self, instance: &str, class: &str) -> bool {
+        self.match_class == class || self.match_class == instance
+    }
+
+    /// Get the size of the scratchpad, as fractions of the work area.
+    ///
+    /// Each fraction is clamped between `0.1` and `1.0`.
+    pub fn get_size(&self) -> (f32, f32) {
+        (self.width.clamp(0.1, 1.0), self.height.clamp(0.1, 1.0))
+    }
 }
 
 /// Global window manager configurations.
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct Config {
     actions: Vec<ActionConfig>,
+    #[serde(default)]
+    rules: Vec<Rule>,
+    /// Desktops, in the order they are numbered. Monitors without any get
+    /// a single desktop.
+    #[serde(default)]
+    desktops: Vec<DesktopConfig>,
+    #[serde(default)]
+    layout: LayoutConfig,
+    /// Name of the cursor shown over the root window.
+    cursor: Option<String>,
+    scratchpad: Option<ScratchpadConfig>,
+    /// How long to wait for the next key of a sequence, in milliseconds.
+    chord_timeout: Option<u64>,
+    /// How many log files to keep, including the current one.
+    keep_logs: Option<usize>,
+    /// Most verbose level logged, from `off` and `error` to `trace`.
+    log_level: Option<String>,
+    /// Commands run in order when the window manager starts.
+    #[serde(default)]
+    autostart: Vec<String>,
 }
 
+/// Default configuration, used when the user has not created a config file.
+const DEFAULT_CONFIG: &str = include_str!("../../assets/config.toml");
+
 impl Config {
-    #[must_use]
-    pub fn from_str(config: &str) -> Self {
-        // TODO: propagate `Result`.
-        toml::from_str(config).unwrap()
+    /// Parse a configuration from a TOML string.
+    pub fn from_str(config: &str) -> NerdResult<Self> {
+        toml::from_str(config).map_err(|e| Error::Other(format!("invalid config: {}", e)))
+    }
+
+    /// Load the configuration from `$XDG_CONFIG_HOME/nerdwm/config.toml`.
+    ///
+    /// Falls back to the default configuration if the file does not exist.
+    pub fn load() -> NerdResult<Self> {
+        match get_xdg_dirs().find_config_file("config.toml") {
+            Some(path) => {
+                info!("Loading config from {:?}", path);
+                let config_str = std::fs::read_to_string(&path)
+                    .map_err(|e| Error::Other(format!("unable to read {:?}: {}", path, e)))?;
+                Self::from_str(&config_str)
+            }
+            None => {
+                info!("No config file found, using defaults");
+                Self::from_str(DEFAULT_CONFIG)
+            }
+        }
+    }
+
+    /// Replace modifiers named after keys, such as `Super`, with the modifiers
+    /// they are mapped to in `map`. This must be done before any binding is used.
+    pub fn resolve_modifiers(&mut self, map: &input::ModifierMap) {
+        for action in &mut self.actions {
+            for bind in [&mut action.keybind, &mut action.tapbind]
+                .into_iter()
+                .flatten()
+            {
+                bind.resolve_modifiers(map);
+            }
+            if let Some(bind) = &mut action.mousebind {
+                bind.resolve_modifiers(map);
+            }
+        }
+    }
+
+    /// Log the configuration as it was parsed, along with the modifier
+    /// masks each binding resolves to.
+    pub fn log(&self) {
+        info!("Using config: {:#?}", self);
+
+        for action in &self.actions {
+            let ty = action.get_type();
+            if let Some(k) = action.get_keybind() {
+                info!(
+                    "{:?} bound to key {:?}, modifier mask {:#04x}",
+                    ty,
+                    k.get_keysym(),
+                    k.get_modifier_mask()
+                );
+                for next in k.get_sequence() {
+                    info!(
+                        "  followed by key {:?}, modifier mask {:#04x}",
+                        next.get_keysym(),
+                        next.get_modifier_mask()
+                    );
+                }
+            }
+            if let Some(b) = action.get_mousebind() {
+                info!(
+                    "{:?} bound to button {:?}, modifier mask {:#04x}",
+                    ty,
+                    b.get_button(),
+                    b.get_modifier_mask()
+                );
+            }
+            if let Some(t) = action.get_tapbind() {
+                info!(
+                    "{:?} bound to tapping {:?}, modifier mask {:#04x}",
+                    ty,
+                    t.get_keysym(),
+                    t.get_modifier_mask()
+                );
+            }
+        }
+    }
+
+    /// Look for mistakes that parsing can't catch.
+    ///
+    /// Every bound key is resolved against the current keymap, if one is given.
+    /// Returns a description of each problem found.
+    pub fn check(&self, keysyms: Option<&keyconvert::KeySymbols>) -> Vec<String> {
+        let mut problems = vec![];
+
+        if let Some(level) = &self.log_level {
+            if level.parse::<log::LevelFilter>().is_err() {
+                problems.push(format!("{:?} is not a log level", level));
+            }
+        }
+
+        let layouts = self.layout.get_layouts().iter().map(String::as_str);
+        let starting = self.desktops.iter().filter_map(DesktopConfig::get_layout);
+        for name in layouts.chain(starting) {
+            if !layout::NAMES.contains(&name) {
+                problems.push(format!("{:?} is not a layout", name));
+            }
+        }
+
+        for action in &self.actions {
+            let ty = action.get_type();
+            if ty == actions::ActionType::Spawn && action.get_command().is_none() {
+                problems.push(format!("{:?} has no command", ty));
+            }
+
+            let keysyms = match keysyms {
+                Some(k) => k,
+                None => continue,
+            };
+            let keys = action
+                .get_keybind()
+                .iter()
+                .flat_map(|k| std::iter::once(k).chain(k.get_sequence()))
+                .chain(action.get_tapbind());
+            for key in keys {
+                if keysyms
+                    .get_keycode(key.get_keysym() as u32)
+                    .next()
+                    .is_none()
+                {
+                    problems.push(format!(
+                        "{:?} is bound to {:?}, which isn't on the keyboard",
+                        ty,
+                        key.get_keysym()
+                    ));
+                }
+            }
+        }
+
+        problems
     }
 
     pub fn get_actions(&self) -> &Vec<ActionConfig> {
         &self.actions
     }
+
+    pub fn get_rules(&self) -> &Vec<Rule> {
+        &self.rules
+    }
+
+    pub fn get_desktops(&self) -> &Vec<DesktopConfig> {
+        &self.desktops
+    }
+
+    pub fn get_layout(&self) -> &LayoutConfig {
+        &self.layout
+    }
+
+    /// Get the name of the cursor shown over the root window. Defaults to `left_ptr`.
+    pub fn get_cursor(&self) -> &str {
+        self.cursor.as_deref().unwrap_or("left_ptr")
+    }
+
+    /// Get how long to wait for the next key of a key sequence. Defaults to one second.
+    pub fn get_chord_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.chord_timeout.unwrap_or(1000))
+    }
+
+    /// Get how many log files to keep, including the current one. Defaults to 10.
+    pub fn get_keep_logs(&self) -> usize {
+        self.keep_logs.unwrap_or(10)
+    }
+
+    /// Get the configured log level, if it is set to a valid level.
+    pub fn get_log_level(&self) -> Option<log::LevelFilter> {
+        self.log_level.as_ref()?.parse().ok()
+    }
+
+    /// Set the log level, preferring [`LOG_LEVEL_VAR`] over the config, and
+    /// falling back to [`get_default_log_level`].
+    pub fn apply_log_level(&self) {
+        let level = get_env_log_level()
+            .or_else(|| self.get_log_level())
+            .unwrap_or_else(get_default_log_level);
+        log::set_max_level(level);
+    }
+
+    /// Get the commands run when the window manager starts.
+    pub fn get_autostart(&self) -> &[String] {
+        &self.autostart
+    }
+
+    /// Get the scratchpad, if one is configured.
+    pub fn get_scratchpad(&self) -> Option<&ScratchpadConfig> {
+        self.scratchpad.as_ref()
+    }
+
+    /// Find the rule to apply to a window with the given instance and class names.
+    ///
+    /// Rules are checked in the order they are defined, and the first rule
+    /// whose `match_class` equals either the class or the instance name wins.
+    /// Any later rules that also match are ignored.
+    pub fn match_rule(&self, instance: &str, class: &str) -> Option<&Rule> {
+        self.rules.iter().find(|r| r.matches(instance, class))
+    }
 }
@@ -0,0 +1,84 @@
+//! Cursors from the core X `cursor` font.
+//!
+//! Cursors are looked up by the names used in `X11/cursorfont.h`, without
+//! the `XC_` prefix. The supported names are:
+//!
+//! | Name                | Shape                               |
+//! |---------------------|-------------------------------------|
+//! | `X_cursor`          | Cross, used when nothing is set     |
+//! | `arrow`             | Arrow pointing up and to the right  |
+//! | `left_ptr`          | Arrow pointing up and to the left   |
+//! | `crosshair`         | Crosshair                           |
+//! | `fleur`             | Four-way arrow, for moving          |
+//! | `hand1`, `hand2`    | Pointing hands                      |
+//! | `question_arrow`    | Arrow with a question mark          |
+//! | `sizing`            | Resize box                          |
+//! | `top_left_corner`, `top_right_corner`       | Corner resize  |
+//! | `bottom_left_corner`, `bottom_right_corner` | Corner resize  |
+//! | `sb_h_double_arrow`, `sb_v_double_arrow` | Edge resize    |
+//! | `watch`             | Busy                                |
+//! | `xterm`             | Text insertion bar                  |
+
+use crate::prelude::*;
+
+/// Glyphs in the `cursor` font, by name.
+///
+/// Each glyph is followed by its mask in the font.
+const GLYPHS: &[(&str, u16)] = &[
+    ("X_cursor", 0),
+    ("arrow", 2),
+    ("bottom_left_corner", 12),
+    ("bottom_right_corner", 14),
+    ("crosshair", 34),
+    ("fleur", 52),
+    ("hand1", 58),
+    ("hand2", 60),
+    ("left_ptr", 68),
+    ("question_arrow", 92),
+    ("sb_h_double_arrow", 108),
+    ("sb_v_double_arrow", 116),
+    ("sizing", 120),
+    ("top_left_corner", 134),
+    ("top_right_corner", 136),
+    ("watch", 150),
+    ("xterm", 152),
+];
+
+/// Get the glyph of a cursor in the `cursor` font by name.
+pub fn get_glyph(name: &str) -> Option<u16> {
+    GLYPHS.iter().find(|(n, _)| *n == name).map(|(_, g)| *g)
+}
+
+/// Create a cursor by name. See the [module documentation](self) for supported names.
+///
+/// The cursor is drawn in black, with a white outline.
+pub fn get_cursor_named(conn: &xcb::Connection, name: &str) -> NerdResult<xcb::Cursor> {
+    let glyph = match get_glyph(name) {
+        Some(g) => g,
+        None => return Err(Error::Other(format!("unknown cursor `{}`", name))),
+    };
+
+    let font = conn.generate_id();
+    xcb::open_font_checked(conn, font, "cursor").request_check()?;
+
+    let cursor = conn.generate_id();
+    let result = xcb::create_glyph_cursor_checked(
+        conn,
+        cursor,
+        font,
+        font,
+        glyph,
+        glyph + 1,
+        0,
+        0,
+        0,
+        0xffff,
+        0xffff,
+        0xffff,
+    )
+    .request_check();
+    xcb::close_font(conn, font);
+    result?;
+
+    Ok(cursor)
+}
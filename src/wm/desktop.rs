@@ -4,14 +4,212 @@
 
 #![allow(unused)]
 
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::sync::Arc;
 
+use tokio::time::Instant;
+
 use super::actions::{Action, ActionType};
+use super::animation::{self, Animation};
+use super::client::{ClientWindow, StackLevel, TitlebarButton, WindowProperties};
+use super::config;
+use super::cursor;
 use super::events::Event;
 use super::ewmh;
+use super::geometry::Rectangle;
 use super::layout;
+use super::stack::Stack;
 use crate::prelude::*;
 
+/// Check if a window has `override_redirect` set.
+///
+/// Such windows, like tooltips and menus, are handled directly by the X server
+/// and should never be managed.
+pub fn is_override_redirect(conn: &xcb::Connection, window: xcb::Window) -> NerdResult<bool> {
+    Ok(xcb::get_window_attributes(conn, window)
+        .get_reply()?
+        .override_redirect())
+}
+
+/// Move and resize a tiled client.
+///
+/// Requests are not checked or flushed in release builds, so that a whole
+/// layout is sent to the server in one batch. Debug builds check every
+/// request, so that errors are reported where they happen.
+fn place(conn: &xcb::Connection, client: &ClientWindow, geometry: Rectangle) -> NerdResult<()> {
+    if cfg!(debug_assertions) {
+        client.configure(conn, geometry)
+    } else {
+        client.configure_unchecked(conn, geometry);
+        Ok(())
+    }
+}
+
+/// Move a span starting at `start` and `length` long, so whichever of its ends
+/// is nearest to one of `targets` lines up with it, if it is within `threshold`.
+fn snap_edge(start: i16, length: u16, targets: impl Iterator<Item = i16>, threshold: i32) -> i16 {
+    let end = start as i32 + length as i32;
+    targets
+        .flat_map(|t| [t as i32 - start as i32, t as i32 - end])
+        .filter(|offset| offset.abs() <= threshold)
+        .min_by_key(|offset| offset.abs())
+        .map_or(start, |offset| (start as i32 + offset) as i16)
+}
+
+/// Part of a window that is dragged to resize it.
+///
+/// The opposite edges stay in place while resizing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResizeGrip {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl ResizeGrip {
+    /// Find the grip nearest to `(x, y)`, for a window with the given geometry.
+    ///
+    /// The window is split into thirds along each axis. The middle of the
+    /// window resizes from the bottom right corner.
+    #[must_use]
+    pub fn from_position(geometry: Rectangle, x: i16, y: i16) -> Self {
+        let Rectangle {
+            x: window_x,
+            y: window_y,
+            width,
+            height,
+        } = geometry;
+        let third = |offset: i16, length: u16| {
+            let offset = offset as i32 * 3;
+            if offset < length as i32 {
+                0
+            } else if offset < length as i32 * 2 {
+                1
+            } else {
+                2
+            }
+        };
+
+        match (third(x - window_x, width), third(y - window_y, height)) {
+            (0, 0) => Self::TopLeft,
+            (1, 0) => Self::Top,
+            (2, 0) => Self::TopRight,
+            (0, 1) => Self::Left,
+            (0, _) => Self::BottomLeft,
+            (1, 2) => Self::Bottom,
+            (2, 1) => Self::Right,
+            _ => Self::BottomRight,
+        }
+    }
+
+    /// Find the corner nearest to `(x, y)`, for a window with the given geometry.
+    ///
+    /// The window is split into quadrants, so only corners are ever picked.
+    #[must_use]
+    pub fn from_quadrant(geometry: Rectangle, x: i16, y: i16) -> Self {
+        let left = (x - geometry.x) as i32 * 2 < geometry.width as i32;
+        let top = (y - geometry.y) as i32 * 2 < geometry.height as i32;
+
+        match (left, top) {
+            (true, true) => Self::TopLeft,
+            (false, true) => Self::TopRight,
+            (true, false) => Self::BottomLeft,
+            (false, false) => Self::BottomRight,
+        }
+    }
+
+    /// Name of the cursor shown while dragging this grip.
+    pub fn get_cursor(&self) -> &'static str {
+        match self {
+            Self::TopLeft => "top_left_corner",
+            Self::TopRight => "top_right_corner",
+            Self::BottomLeft => "bottom_left_corner",
+            Self::BottomRight => "bottom_right_corner",
+            Self::Left | Self::Right => "sb_h_double_arrow",
+            Self::Top | Self::Bottom => "sb_v_double_arrow",
+        }
+    }
+
+    /// Apply a pointer movement of `(dx, dy)` to `geometry`.
+    ///
+    /// The width and height are kept at least `min_size`.
+    pub fn resize(&self, geometry: Rectangle, (dx, dy): (i16, i16), min_size: u16) -> Rectangle {
+        let Rectangle {
+            x,
+            y,
+            width,
+            height,
+        } = geometry;
+
+        // Moving the left or top edge changes the size in the opposite
+        // direction, and moves the window by however much it changed.
+        let grow =
+            |length: u16, delta: i16| (length as i32 + delta as i32).max(min_size as i32) as u16;
+        let shrink = |start: i16, length: u16, delta: i16| {
+            let new = grow(length, -delta);
+            (start + (length as i32 - new as i32) as i16, new)
+        };
+
+        let (x, width) = match self {
+            Self::TopLeft | Self::Left | Self::BottomLeft => shrink(x, width, dx),
+            Self::TopRight | Self::Right | Self::BottomRight => (x, grow(width, dx)),
+            Self::Top | Self::Bottom => (x, width),
+        };
+        let (y, height) = match self {
+            Self::TopLeft | Self::Top | Self::TopRight => shrink(y, height, dy),
+            Self::BottomLeft | Self::Bottom | Self::BottomRight => (y, grow(height, dy)),
+            Self::Left | Self::Right => (y, height),
+        };
+        Rectangle::new(x, y, width, height)
+    }
+}
+
+/// Part of the desktop a client can be snapped to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapEdge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    /// The whole desktop.
+    Full,
+}
+
+impl SnapEdge {
+    /// Get the part of `area` taken by a client snapped to this edge,
+    /// with the given fraction of the width or height of `area`.
+    #[must_use]
+    pub fn get_geometry(self, area: Rectangle, fraction: f32) -> Rectangle {
+        match self {
+            Self::Left => area.split_horizontal(fraction, 0).0,
+            Self::Right => area.split_horizontal(1.0 - fraction, 0).1,
+            Self::Top => area.split_vertical(fraction, 0).0,
+            Self::Bottom => area.split_vertical(1.0 - fraction, 0).1,
+            Self::Full => area,
+        }
+    }
+}
+
+/// A client snapped to part of the desktop.
+#[derive(Debug, Clone, Copy)]
+struct Snapped {
+    window: xcb::Window,
+    edge: SnapEdge,
+    /// Whether the client takes a third of the desktop instead of half.
+    /// This changes each time the client is snapped to the same edge again.
+    third: bool,
+    /// Geometry to restore when the client is unsnapped.
+    geometry: Rectangle,
+    /// Whether the client was floating before it was snapped.
+    floating: bool,
+}
+
 /// Structure containing all clients on a virtual desktop, or workspace.
 ///
 /// Clients owned by this desktop will always need to be visible.
@@ -22,14 +220,65 @@ use crate::prelude::*;
 pub struct Desktop {
     name: String,
     conn: Arc<xcb::Connection>,
-    clients: Vec<xcb::Window>,
-    layout_mgr: Box<dyn layout::Layout>,
+    /// Clients, indexed by every window they own.
+    clients: Stack<ClientWindow>,
+    /// Client window with input focus.
+    focused: Option<xcb::Window>,
+    /// Clients in the order they were last focused, most recent first.
+    focus_history: Vec<xcb::Window>,
+    /// Position in `focus_history` while cycling through it. The history
+    /// is only reordered once the cycle is committed.
+    history_cycle: Option<usize>,
+    /// Client that was in the master position before the last [`Desktop::swap_master`].
+    previous_master: Option<xcb::Window>,
+    /// Clients that are not managed by the layout.
+    floating: Vec<xcb::Window>,
+    /// Clients covering the whole desktop, along with the geometry
+    /// to restore when they are no longer maximized.
+    maximized: Vec<(xcb::Window, Rectangle)>,
+    /// Clients snapped to part of the desktop. These are always floating.
+    snapped: Vec<Snapped>,
+    /// Dialogs, along with the client they belong to. Dialogs are kept
+    /// floating, and are raised along with their parent.
+    transients: Vec<(xcb::Window, xcb::Window)>,
+    /// Layouts that can be cycled through.
+    layouts: Vec<Box<dyn layout::Layout>>,
+    /// Index of the layout currently managing clients.
+    layout: usize,
+    /// Area available to tiled clients.
+    area: Rectangle,
+    /// Area new floating clients are centered in, such as the monitor under
+    /// the pointer. The whole desktop is used if this is [`None`].
+    placement: Option<Rectangle>,
+    /// Geometry each tiled client was last given by the layout.
+    placed: HashMap<xcb::Window, Rectangle>,
+    /// Tiled clients still moving to their place.
+    animations: Vec<Animation>,
+    /// When the animations were last stepped.
+    last_frame: Instant,
+    /// Clients asked to close, along with when they are killed if they are
+    /// still around.
+    closing: Vec<(xcb::Window, Instant)>,
+    /// Whether clients were restacked since [`Desktop::take_restacked`] was last called.
+    restacked: Cell<bool>,
+    /// Appearance of clients.
+    config: config::LayoutConfig,
     ewmh_mgr: Arc<ewmh::EWMHManager>,
     // internal window stuff
     // ---------------------
     /// Last known mouse position.
     /// Used to determine scale of window resizing/movement.
     last_mouse: Option<(i16, i16)>,
+    /// Window currently being moved with the pointer, along with its geometry
+    /// when the move started. This is the client window for managed clients,
+    /// so the drag survives the pointer leaving it.
+    moving: Option<(xcb::Window, Rectangle)>,
+    /// Edges a moved window snaps to: the desktop and the other clients on it,
+    /// as they were when the move started.
+    snap_targets: Vec<Rectangle>,
+    /// Window currently being resized with the pointer, along with the grip
+    /// being dragged and its geometry when the resize started.
+    resizing: Option<(xcb::Window, ResizeGrip, Rectangle)>,
 }
 
 impl Desktop {
@@ -37,16 +286,41 @@ impl Desktop {
     pub fn new(
         conn: Arc<xcb::Connection>,
         name: String,
-        layout_mgr: Box<dyn layout::Layout>,
+        mut layouts: Vec<Box<dyn layout::Layout>>,
+        config: config::LayoutConfig,
         ewmh_mgr: Arc<ewmh::EWMHManager>,
     ) -> Self {
+        if layouts.is_empty() {
+            layouts.push(Box::new(layout::BlankLayout::default()));
+        }
+
         Self {
             name,
             conn,
-            clients: vec![],
-            layout_mgr,
+            clients: Stack::new(),
+            focused: None,
+            focus_history: vec![],
+            history_cycle: None,
+            previous_master: None,
+            floating: vec![],
+            transients: vec![],
+            maximized: vec![],
+            snapped: vec![],
+            layouts,
+            layout: 0,
+            area: Rectangle::default(),
+            placement: None,
+            placed: HashMap::new(),
+            animations: vec![],
+            last_frame: Instant::now(),
+            closing: vec![],
+            restacked: Cell::new(false),
+            config,
             ewmh_mgr,
             last_mouse: None,
+            moving: None,
+            snap_targets: vec![],
+            resizing: None,
         }
     }
 
@@ -55,98 +329,1577 @@ impl Desktop {
         &self.name
     }
 
-    /// Get a stack of clients owned by this desktop. The active window is
-    /// always on the top of the stack.
-    pub fn get_clients(&self) -> &Vec<xcb::Window> {
+    /// Set the area new floating clients are centered in.
+    pub fn set_placement(&mut self, area: Rectangle) {
+        self.placement = Some(area);
+    }
+
+    /// Set the area available to tiled clients, and reconfigure them to fit.
+    pub fn set_area(&mut self, area: Rectangle) -> NerdResult<()> {
+        self.area = area;
+        self.arrange()
+    }
+
+    /// Change the appearance of clients, and apply it to existing clients.
+    ///
+    /// Titlebar changes only apply to clients managed after this.
+    pub fn set_config(&mut self, config: config::LayoutConfig) -> NerdResult<()> {
+        self.config = config;
+        self.update_focus()?;
+        for client in self.clients.iter() {
+            let (left, right, top, bottom) = client.get_frame_extents();
+            self.ewmh_mgr
+                .set_frame_extents(client.get_window(), left, right, top, bottom)?;
+        }
+        self.arrange()
+    }
+
+    /// Get a stack of clients owned by this desktop. This is the order
+    /// the layout tiles clients in, with the master client on top.
+    pub fn get_clients(&self) -> &[ClientWindow] {
         &self.clients
     }
 
-    /// Push a window to the stack and focus it.
-    pub fn focus(&mut self, client: xcb::Window) -> NerdResult<()> {
-        // Push the client onto the top of the stack.
-        if let Some(p) = self.clients.iter().position(|c| c == &client) {
-            // If this desktop already holds the client,
-            // move it to the front of the stack.
-            let client = self.clients.remove(p);
+    /// Get the client with input focus.
+    pub fn get_focused(&self) -> Option<&ClientWindow> {
+        self.focused
+            .and_then(|f| self.position(f))
+            .map(|p| &self.clients[p])
+    }
+
+    /// Get the client windows owned by this desktop, in stacking order.
+    pub fn get_windows(&self) -> Vec<xcb::Window> {
+        self.clients.iter().map(|c| c.get_window()).collect()
+    }
+
+    /// Find the position of a client in the stack, given either
+    /// its window or its frame.
+    fn position(&self, window: xcb::Window) -> Option<usize> {
+        self.clients.position(window)
+    }
+
+    /// Check if this desktop manages a window, given either the client window or its frame.
+    pub fn contains(&self, window: xcb::Window) -> bool {
+        self.position(window).is_some()
+    }
+
+    /// Check if `window` is the titlebar of a client on this desktop.
+    pub fn is_titlebar(&self, window: xcb::Window) -> bool {
+        self.position(window)
+            .is_some_and(|p| self.clients[p].is_titlebar(window))
+    }
+
+    /// Redraw the titlebar of a client, given any of its windows.
+    ///
+    /// Returns `false` if the window isn't managed by this desktop.
+    pub fn redraw_title(&self, window: xcb::Window) -> NerdResult<bool> {
+        if self.layouts[self.layout].owns(window) {
+            self.redraw_layout()?;
+            return Ok(true);
+        }
+
+        let client = match self.position(window) {
+            Some(p) => &self.clients[p],
+            None => return Ok(false),
+        };
+
+        if let Some(titlebar) = client.get_titlebar() {
+            let title = self
+                .ewmh_mgr
+                .get_name(client.get_window())?
+                .unwrap_or_default();
+            titlebar.draw(&self.conn, &title)?;
+        }
+        Ok(true)
+    }
+
+    /// React to a change of one of a client's properties.
+    ///
+    /// `property` is the atom identifying the property.
+    /// Returns `false` if the window isn't managed by this desktop.
+    pub fn property_changed(
+        &mut self,
+        window: xcb::Window,
+        property: xcb::Atom,
+    ) -> NerdResult<bool> {
+        if !self.get_windows().contains(&window) {
+            return Ok(false);
+        }
+
+        // ICCCM properties have predefined atoms, and EWMH atoms are interned
+        // at startup, so none of this waits for the server.
+        let net_wm_name = self.ewmh_mgr.get_atom(ewmh::protocols::_NET_WM_NAME)?;
+        let net_wm_state = self.ewmh_mgr.get_atom(ewmh::protocols::_NET_WM_STATE)?;
+        match property {
+            p if p == xcb::ATOM_WM_NAME || p == net_wm_name => {
+                self.redraw_title(window)?;
+                self.redraw_layout()?;
+            }
+            xcb::ATOM_WM_HINTS => {
+                // Focused clients already have the user's attention.
+                let urgent = self.ewmh_mgr.is_urgent(window)? && self.focused != Some(window);
+                if self.position(window).map(|p| self.clients[p].is_urgent()) != Some(urgent) {
+                    trace!("Urgency of {} changed to {}", window, urgent);
+                    self.set_urgent(window, urgent)?;
+                    self.update_borders()?;
+                }
+            }
+            xcb::ATOM_WM_NORMAL_HINTS => {
+                trace!("Size hints of {} changed", window);
+                self.arrange()?;
+            }
+            p if p == net_wm_state => {
+                trace!("State of {} changed", window);
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Get the button on a client's titlebar at `(x, y)`, relative to the titlebar.
+    pub fn get_titlebar_button(
+        &self,
+        titlebar: xcb::Window,
+        x: i16,
+        y: i16,
+    ) -> NerdResult<Option<TitlebarButton>> {
+        match self
+            .clients
+            .iter()
+            .find(|c| c.is_titlebar(titlebar))
+            .and_then(|c| c.get_titlebar())
+        {
+            Some(t) => t.get_button_at(&self.conn, x, y),
+            None => Ok(None),
+        }
+    }
+
+    /// Redraw anything drawn by the current layout, like tabs.
+    fn redraw_layout(&self) -> NerdResult<()> {
+        self.layouts[self.layout].draw(
+            &self.conn,
+            &self.ewmh_mgr,
+            &self.get_tiled_clients()[..],
+            self.focused,
+        )
+    }
+
+    /// Get the client drawn by the layout at `(x, y)`, relative to `window`.
+    ///
+    /// This is how tabs are mapped to their clients.
+    pub fn get_layout_client(&self, window: xcb::Window, x: i16, y: i16) -> Option<xcb::Window> {
+        self.layouts[self.layout]
+            .get_client_at(window, x, y)
+            .and_then(|p| self.get_tiled_clients().get(p).map(|c| c.get_window()))
+    }
+
+    /// Get the clients whose geometry is managed by the layout.
+    pub fn get_tiled_clients(&self) -> Vec<ClientWindow> {
+        self.clients
+            .iter()
+            .filter(|c| {
+                !self.floating.contains(&c.get_window()) && !self.is_maximized(c.get_window())
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Check if a client covers the whole desktop.
+    pub fn is_maximized(&self, client: xcb::Window) -> bool {
+        self.maximized.iter().any(|(c, _)| c == &client)
+    }
+
+    /// Get the geometry of a client when it isn't maximized.
+    pub fn get_normal_geometry(&self, window: xcb::Window) -> NerdResult<Option<Rectangle>> {
+        let client = match self.position(window) {
+            Some(p) => &self.clients[p],
+            None => return Ok(None),
+        };
+
+        match self
+            .maximized
+            .iter()
+            .find(|(c, _)| c == &client.get_window())
+        {
+            Some((_, geometry)) => Ok(Some(*geometry)),
+            None => Ok(Some(client.get_geometry(&self.conn)?)),
+        }
+    }
+
+    /// Move and resize a floating client. Tiled clients are left to the layout.
+    pub fn place_floating(&self, window: xcb::Window, geometry: Rectangle) -> NerdResult<()> {
+        match self.position(window) {
+            Some(p) if self.is_floating(self.clients[p].get_window()) => {
+                self.clients[p].configure(&self.conn, geometry)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Handle a client's request to move, resize or restack itself.
+    ///
+    /// Floating clients are moved and resized as requested, with the frame
+    /// placed where the client asked to be. The layout owns the geometry of
+    /// tiled and maximized clients, so they are only told where they are.
+    /// Only floating clients may raise themselves, within their level.
+    ///
+    /// Returns `false` if the window isn't managed by this desktop.
+    pub fn configure_request(&self, event: &xcb::ConfigureRequestEvent) -> NerdResult<bool> {
+        let window = event.window();
+        let client = match self.position(window) {
+            Some(p) if self.clients[p].get_window() == window => &self.clients[p],
+            _ => return Ok(false),
+        };
+        let mask = event.value_mask();
+
+        if !self.is_floating(window) || self.is_maximized(window) {
+            client.notify_geometry(&self.conn)?;
+            return Ok(true);
+        }
+
+        let mut geometry = client.get_geometry(&self.conn)?;
+        let (left, right, top, bottom) = client.get_frame_extents();
+        if mask & xcb::CONFIG_WINDOW_X as u16 != 0 {
+            geometry.x = event.x();
+        }
+        if mask & xcb::CONFIG_WINDOW_Y as u16 != 0 {
+            geometry.y = event.y();
+        }
+        if mask & xcb::CONFIG_WINDOW_WIDTH as u16 != 0 {
+            geometry.width = event.width().saturating_add((left + right) as u16);
+        }
+        if mask & xcb::CONFIG_WINDOW_HEIGHT as u16 != 0 {
+            geometry.height = event.height().saturating_add((top + bottom) as u16);
+        }
+        trace!("Configuring floating client {} to {:?}", window, geometry);
+        client.configure(&self.conn, geometry)?;
+
+        if mask & xcb::CONFIG_WINDOW_STACK_MODE as u16 != 0
+            && mask & xcb::CONFIG_WINDOW_SIBLING as u16 == 0
+            && event.stack_mode() == xcb::STACK_MODE_ABOVE as u8
+        {
+            client.raise(&self.conn)?;
+            self.restack()?;
+        }
+        Ok(true)
+    }
+
+    /// Toggle between covering the whole desktop and the client's normal geometry.
+    ///
+    /// The desktop area excludes space reserved by docks, so maximized clients
+    /// don't cover them. The client's `_NET_WM_STATE` is updated to match.
+    ///
+    /// Tiled clients are handed back to the layout when restored, while floating
+    /// clients are moved back to where they were before being maximized.
+    pub fn toggle_maximized(&mut self, window: xcb::Window) -> NerdResult<()> {
+        let client = match self.position(window) {
+            Some(p) => self.clients[p],
+            None => return Ok(()),
+        };
+
+        match self
+            .maximized
+            .iter()
+            .position(|(c, _)| c == &client.get_window())
+        {
+            Some(p) => {
+                let (_, geometry) = self.maximized.remove(p);
+                if self.is_floating(client.get_window()) {
+                    client.configure(&self.conn, geometry)?;
+                }
+                self.ewmh_mgr.set_maximized(client.get_window(), false)?;
+            }
+            None => {
+                let geometry = client.get_geometry(&self.conn)?;
+                self.maximized.push((client.get_window(), geometry));
+                client.raise(&self.conn)?;
+                self.restack()?;
+                self.ewmh_mgr.set_maximized(client.get_window(), true)?;
+            }
+        }
+        self.update_allowed_actions(client.get_window())?;
+        self.arrange()
+    }
+
+    /// Snap a client to part of the desktop, making it floating.
+    ///
+    /// Snapping a client to the same side edge again switches it between
+    /// half and a third of the desktop.
+    pub fn snap(&mut self, window: xcb::Window, edge: SnapEdge) -> NerdResult<()> {
+        let client = match self.position(window) {
+            Some(p) => self.clients[p],
+            None => return Ok(()),
+        };
+        let window = client.get_window();
+        if self.is_maximized(window) {
+            self.toggle_maximized(window)?;
+        }
+
+        let snapped = match self.snapped.iter_mut().find(|s| s.window == window) {
+            Some(snapped) => {
+                snapped.third = snapped.edge == edge && !snapped.third;
+                snapped.edge = edge;
+                *snapped
+            }
+            None => {
+                let snapped = Snapped {
+                    window,
+                    edge,
+                    third: false,
+                    geometry: client.get_geometry(&self.conn)?,
+                    floating: self.is_floating(window),
+                };
+                self.snapped.push(snapped);
+                snapped
+            }
+        };
+        let fraction = if snapped.third { 1.0 / 3.0 } else { 0.5 };
+
+        if !self.is_floating(window) {
+            self.floating.push(window);
+            self.update_allowed_actions(window)?;
+        }
+        client.configure(&self.conn, edge.get_geometry(self.area, fraction))?;
+        client.raise(&self.conn)?;
+        self.restack()
+    }
+
+    /// Restore the geometry a client had before it was snapped, and put it
+    /// back in the layout if it was tiled.
+    pub fn unsnap(&mut self, window: xcb::Window) -> NerdResult<()> {
+        let client = match self.position(window) {
+            Some(p) => self.clients[p],
+            None => return Ok(()),
+        };
+        let snapped = match self
+            .snapped
+            .iter()
+            .position(|s| s.window == client.get_window())
+        {
+            Some(p) => self.snapped.remove(p),
+            None => return Ok(()),
+        };
+
+        if snapped.floating {
+            client.configure(&self.conn, snapped.geometry)?;
+        } else {
+            self.floating.retain(|c| c != &snapped.window);
+            self.update_allowed_actions(snapped.window)?;
+        }
+        Ok(())
+    }
+
+    /// Toggle between keeping a client out of the layout, and tiling it.
+    ///
+    /// Clients that start floating stay where the layout last put them, so
+    /// they don't jump. Snapped clients are tiled again, forgetting the
+    /// geometry they had before being snapped.
+    pub fn toggle_floating(&mut self, window: xcb::Window) -> NerdResult<()> {
+        let window = match self.position(window) {
+            Some(p) => self.clients[p].get_window(),
+            None => return Ok(()),
+        };
+
+        let floating = !self.is_floating(window);
+        if !floating {
+            self.snapped.retain(|s| s.window != window);
+        }
+        trace!("Setting {} floating: {}", window, floating);
+        self.set_floating(window, floating)?;
+        self.restack()
+    }
+
+    /// Check if a client is kept out of the layout.
+    pub fn is_floating(&self, client: xcb::Window) -> bool {
+        self.floating.contains(&client)
+    }
+
+    /// Set whether a client should be kept out of the layout.
+    pub fn set_floating(&mut self, client: xcb::Window, floating: bool) -> NerdResult<()> {
+        let pos = self.floating.iter().position(|c| c == &client);
+        match (floating, pos) {
+            (true, None) => self.floating.push(client),
+            (false, Some(p)) => {
+                self.floating.remove(p);
+            }
+            _ => return Ok(()),
+        }
+        self.update_allowed_actions(client)?;
+        self.arrange()
+    }
+
+    /// Tell a client which actions it allows, which depends on whether it is
+    /// floating and not maximized.
+    ///
+    /// This must be called when a client is framed, and whenever it starts or
+    /// stops floating or being maximized. Windows not managed by this desktop
+    /// are ignored.
+    fn update_allowed_actions(&self, window: xcb::Window) -> NerdResult<()> {
+        if !self.contains(window) {
+            return Ok(());
+        }
+        let floating = self.is_floating(window) && !self.is_maximized(window);
+        self.ewmh_mgr.set_allowed_actions(window, floating)
+    }
+
+    /// Mark a client as a dialog belonging to `parent`, so it is centered on
+    /// its parent when it is framed, and stays above it.
+    pub fn set_transient_for(&mut self, client: xcb::Window, parent: xcb::Window) {
+        self.transients.retain(|(c, _)| c != &client);
+        self.transients.push((client, parent));
+    }
+
+    /// Get the dialogs belonging to a client.
+    fn get_transients(&self, parent: xcb::Window) -> impl Iterator<Item = &ClientWindow> {
+        self.transients
+            .iter()
+            .filter(move |(_, p)| p == &parent)
+            .filter_map(|(c, _)| self.position(*c))
+            .map(|p| &self.clients[p])
+    }
+
+    /// Reconfigure the geometry of all tiled clients.
+    ///
+    /// If animations are enabled, clients that were already tiled slide to
+    /// their new place, starting from wherever they are now.
+    fn arrange(&mut self) -> NerdResult<()> {
+        let geometries = self.layouts[self.layout].configure(
+            &self.conn,
+            self.area,
+            &self.config,
+            &self.get_tiled_clients()[..],
+        )?;
+
+        let now = Instant::now();
+        let mut placed = HashMap::new();
+        for (window, geometry) in geometries {
+            let client = match self.position(window) {
+                Some(p) => self.clients[p],
+                None => continue,
+            };
+
+            let current = match self
+                .animations
+                .iter()
+                .position(|a| a.get_client().get_window() == window)
+            {
+                Some(p) => Some(self.animations.remove(p).get_geometry(now).0),
+                None => self.placed.get(&window).copied(),
+            };
+            match current {
+                Some(from) if self.config.get_animations() && from != geometry => {
+                    if self.animations.is_empty() {
+                        self.last_frame = now;
+                    }
+                    self.animations.push(Animation::new(
+                        client,
+                        from,
+                        geometry,
+                        self.config.get_animation_duration(),
+                    ));
+                }
+                _ => place(&self.conn, &client, geometry)?,
+            }
+            placed.insert(window, geometry);
+        }
+        // Clients that are no longer tiled stop where they are.
+        self.animations
+            .retain(|a| placed.contains_key(&a.get_client().get_window()));
+        self.placed = placed;
+        self.conn.flush();
+
+        for client in self.clients.iter() {
+            let window = client.get_window();
+            if self.is_maximized(window) {
+                client.configure(&self.conn, self.area)?;
+            }
+        }
+        self.redraw_layout()
+    }
+
+    /// Grow the master area of the current layout by `delta`, as a fraction of
+    /// the available area. Does nothing if the layout has no master area.
+    pub fn adjust_master(&mut self, delta: f32) -> NerdResult<()> {
+        if self.layouts[self.layout].adjust_master(delta) {
+            self.arrange()?;
+        }
+        Ok(())
+    }
+
+    /// Switch the orientation of the split holding the focused client.
+    /// Does nothing if the layout has no splits.
+    pub fn rotate_split(&mut self) -> NerdResult<()> {
+        if self.layouts[self.layout].rotate() {
+            self.arrange()?;
+        }
+        Ok(())
+    }
+
+    /// Swap the two sides of the split holding the focused client.
+    /// Does nothing if the layout has no splits.
+    pub fn flip_split(&mut self) -> NerdResult<()> {
+        if self.layouts[self.layout].flip() {
+            self.arrange()?;
+        }
+        Ok(())
+    }
+
+    /// Switch to the layout named `name`.
+    ///
+    /// Returns `false` if this desktop has no such layout.
+    pub fn set_layout(&mut self, name: &str) -> NerdResult<bool> {
+        let index = match self.layouts.iter().position(|l| l.get_name() == name) {
+            Some(i) => i,
+            None => return Ok(false),
+        };
+        if index != self.layout {
+            self.layouts[self.layout].hide(&self.conn)?;
+            self.layout = index;
+            self.arrange()?;
+        }
+        Ok(true)
+    }
+
+    /// Switch to the next layout, wrapping around to the first.
+    pub fn cycle_layout(&mut self) -> NerdResult<()> {
+        self.layouts[self.layout].hide(&self.conn)?;
+        self.layout = (self.layout + 1) % self.layouts.len();
+        self.arrange()
+    }
+
+    /// Frame a window, keeping it in the stacking level requested in its `_NET_WM_STATE`.
+    ///
+    /// Floating windows that didn't ask for a position are centered on the desktop.
+    fn frame(&self, window: xcb::Window) -> NerdResult<ClientWindow> {
+        let mut client = ClientWindow::from_window(
+            &self.conn,
+            window,
+            self.config.get_border_unfocused(),
+            self.config.get_frame_background_unfocused(),
+            self.config.get_titlebar(),
+        )?;
+        let (left, right, top, bottom) = client.get_frame_extents();
+        self.ewmh_mgr
+            .set_frame_extents(window, left, right, top, bottom)?;
+
+        if self
+            .ewmh_mgr
+            .has_state(window, ewmh::protocols::_NET_WM_STATE_ABOVE)?
+        {
+            client.set_level(StackLevel::Above);
+        } else if self
+            .ewmh_mgr
+            .has_state(window, ewmh::protocols::_NET_WM_STATE_BELOW)?
+        {
+            client.set_level(StackLevel::Below);
+        }
+        if self.ewmh_mgr.is_urgent(window)? {
+            client.set_urgent(true);
+            self.ewmh_mgr.set_states(
+                window,
+                &[ewmh::protocols::_NET_WM_STATE_DEMANDS_ATTENTION],
+                true,
+            )?;
+        }
+
+        let parent = self
+            .transients
+            .iter()
+            .find(|(c, _)| c == &window)
+            .and_then(|(_, p)| self.position(*p));
+        if let Some(p) = parent {
+            let area = self.clients[p].get_geometry(&self.conn)?;
+            client.center_on(&self.conn, area)?;
+        } else if self.is_floating(window) {
+            let geometry = client.get_geometry(&self.conn)?;
+            if (geometry.x, geometry.y) == (0, 0) {
+                client.center_on(&self.conn, self.placement.unwrap_or(self.area))?;
+            }
+        }
+        Ok(client)
+    }
+
+    /// Maximize a newly managed client if its `_NET_WM_STATE` says it already is,
+    /// as left by the application or a session manager.
+    ///
+    /// The client's current geometry is kept to restore it later. The client is
+    /// also told which actions it allows, as this is the last step of framing it.
+    fn restore_maximized(&mut self, window: xcb::Window) -> NerdResult<()> {
+        let client = match self.position(window) {
+            Some(p) if !self.is_maximized(window) => self.clients[p],
+            _ => return Ok(()),
+        };
+
+        if self
+            .ewmh_mgr
+            .has_state(window, ewmh::protocols::_NET_WM_STATE_MAXIMIZED_VERT)?
+            || self
+                .ewmh_mgr
+                .has_state(window, ewmh::protocols::_NET_WM_STATE_MAXIMIZED_HORZ)?
+        {
+            let geometry = client.get_geometry(&self.conn)?;
+            self.maximized.push((window, geometry));
+            // Both states are treated as one, so make sure both are set.
+            self.ewmh_mgr.set_maximized(window, true)?;
+        }
+        self.update_allowed_actions(window)
+    }
+
+    /// Frame a window and add it to the bottom of the stack, without mapping
+    /// or focusing it.
+    ///
+    /// This is used for clients placed on a desktop that is not currently visible.
+    /// They will be mapped the next time the desktop is shown.
+    pub fn adopt(&mut self, window: xcb::Window) -> NerdResult<()> {
+        if !self.contains(window) {
+            let client = self.frame(window)?;
+            self.clients.push(client);
+            self.restore_maximized(window)?;
+        }
+        Ok(())
+    }
+
+    /// Frame a window if it isn't already managed by this desktop, then map and focus it.
+    pub fn manage(&mut self, window: xcb::Window) -> NerdResult<()> {
+        if !self.contains(window) {
+            let client = self.frame(window)?;
             self.clients.insert(0, client);
-            self.layout_mgr.configure(&self.clients)?;
+            self.restore_maximized(window)?;
+        }
+
+        if let Some(p) = self.position(window) {
+            self.clients[p].map(&self.conn)?;
+        }
+        self.focus(window)
+    }
+
+    /// Check if a window about to be managed should take the focus.
+    ///
+    /// New windows don't take the focus if the configuration says so, or if
+    /// they asked not to with `_NET_WM_USER_TIME`, unless nothing else is focused.
+    pub fn wants_focus(&self, window: xcb::Window) -> NerdResult<bool> {
+        if self.focused.is_none() {
+            return Ok(true);
+        }
+        Ok(self.config.get_focus_new_windows() && !self.ewmh_mgr.refuses_initial_focus(window)?)
+    }
+
+    /// Frame a window if it isn't already managed by this desktop, and map it
+    /// without taking the focus away from the focused client.
+    ///
+    /// The client is marked as needing attention instead.
+    pub fn manage_unfocused(&mut self, window: xcb::Window) -> NerdResult<()> {
+        if !self.contains(window) {
+            let client = self.frame(window)?;
+            self.clients.insert(0, client);
+            self.restore_maximized(window)?;
+        }
+
+        if let Some(p) = self.position(window) {
+            self.clients[p].map(&self.conn)?;
+        }
+        self.set_urgent(window, true)?;
+        self.update_focus()
+    }
+
+    /// Focus a client, without changing its position in the stack.
+    ///
+    /// `window` can be either the client window or its frame. Windows not
+    /// managed by this desktop are ignored.
+    pub fn focus(&mut self, window: xcb::Window) -> NerdResult<()> {
+        let p = match self.position(window) {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+
+        self.focused = Some(self.clients[p].get_window());
+        self.update_focus()
+    }
+
+    /// Move the focus to the next client in the stack, wrapping around at the end.
+    pub fn focus_next(&mut self) -> NerdResult<()> {
+        self.cycle_focus(1)
+    }
+
+    /// Move the focus to the previous client in the stack, wrapping around at the start.
+    pub fn focus_prev(&mut self) -> NerdResult<()> {
+        self.cycle_focus(self.clients.len().saturating_sub(1))
+    }
+
+    /// Focus the client focused before the one picked last in the focus history,
+    /// wrapping around at the end.
+    ///
+    /// The history keeps its order until [`Desktop::commit_focus_history`],
+    /// so repeated calls walk further back through it.
+    pub fn cycle_focus_history(&mut self) -> NerdResult<()> {
+        if self.history_cycle.is_none() {
+            // Clients that were never focused come last.
+            for client in self.clients.iter() {
+                if !self.focus_history.contains(&client.get_window()) {
+                    self.focus_history.push(client.get_window());
+                }
+            }
+        }
+        if self.focus_history.len() < 2 {
+            return Ok(());
+        }
+
+        let next = (self.history_cycle.unwrap_or_default() + 1) % self.focus_history.len();
+        self.history_cycle = Some(next);
+        self.focused = Some(self.focus_history[next]);
+        self.update_focus()?;
+        self.warp_to_focused()
+    }
+
+    /// Stop cycling through the focus history, and move the client the cycle
+    /// ended on to the front of it.
+    pub fn commit_focus_history(&mut self) -> NerdResult<()> {
+        if self.history_cycle.take().is_some() {
+            self.update_focus()?;
+        }
+        Ok(())
+    }
+
+    /// Move the focus `offset` clients down the stack, wrapping around at the end.
+    fn cycle_focus(&mut self, offset: usize) -> NerdResult<()> {
+        if self.clients.is_empty() {
+            return Ok(());
+        }
+
+        let current = self
+            .focused
+            .and_then(|f| self.position(f))
+            .unwrap_or_default();
+        let next = (current + offset) % self.clients.len();
+        self.focus(self.clients[next].get_window())?;
+        self.warp_to_focused()
+    }
+
+    /// Move the pointer to the center of the focused client, if enabled in the config.
+    ///
+    /// This should only be used when focus changes without the pointer, so the
+    /// pointer ends up over the window that has focus.
+    fn warp_to_focused(&self) -> NerdResult<()> {
+        if !self.config.get_warp_pointer_on_focus() {
+            return Ok(());
+        }
+        match self.get_focused() {
+            Some(client) => client.warp_pointer(&self.conn),
+            None => Ok(()),
+        }
+    }
+
+    /// Move the focused client to the master position, at the top of the stack.
+    ///
+    /// If the focused client is already the master, it is swapped with the
+    /// previous master instead, or with the next tiled client if the previous
+    /// master is gone. The new master is focused. Clients outside the layout
+    /// are ignored.
+    pub fn swap_master(&mut self) -> NerdResult<()> {
+        let tiled = self.get_tiled_clients();
+        let (master, focused) = match (tiled.first(), self.focused) {
+            (Some(m), Some(f)) => (m.get_window(), f),
+            _ => return Ok(()),
+        };
+
+        let promoted = if focused != master {
+            if !tiled.iter().any(|c| c.get_window() == focused) {
+                return Ok(());
+            }
+            focused
         } else {
+            match self
+                .previous_master
+                .filter(|p| tiled.iter().any(|c| &c.get_window() == p))
+                .or_else(|| tiled.get(1).map(|c| c.get_window()))
+            {
+                Some(p) => p,
+                None => return Ok(()),
+            }
+        };
+
+        if let Some(p) = self.position(promoted) {
+            let client = self.clients.remove(p);
             self.clients.insert(0, client);
-            self.layout_mgr.configure(&self.clients)?;
+        }
+        self.previous_master = Some(master);
+
+        self.focus(promoted)?;
+        self.arrange()?;
+        self.warp_to_focused()
+    }
+
+    /// Swap the focused client with the tiled client above it.
+    pub fn move_stack_up(&mut self) -> NerdResult<()> {
+        self.move_stack(false)
+    }
+
+    /// Swap the focused client with the tiled client below it.
+    pub fn move_stack_down(&mut self) -> NerdResult<()> {
+        self.move_stack(true)
+    }
+
+    /// Swap the focused client with its neighbour in the stack, skipping clients
+    /// outside the layout.
+    ///
+    /// At either end of the stack, this wraps around if enabled in the config,
+    /// and does nothing otherwise.
+    fn move_stack(&mut self, down: bool) -> NerdResult<()> {
+        let tiled = self.get_tiled_clients();
+        let current = match self
+            .focused
+            .and_then(|f| tiled.iter().position(|c| c.get_window() == f))
+        {
+            Some(c) => c,
+            None => return Ok(()),
+        };
+
+        let last = tiled.len() - 1;
+        let neighbour = match (down, current) {
+            (true, c) if c == last => self.config.get_wrap_stack().then_some(0),
+            (true, c) => Some(c + 1),
+            (false, 0) => self.config.get_wrap_stack().then_some(last),
+            (false, c) => Some(c - 1),
+        };
+
+        let (a, b) = match neighbour {
+            Some(n) if n != current => (
+                self.position(tiled[current].get_window()),
+                self.position(tiled[n].get_window()),
+            ),
+            _ => return Ok(()),
+        };
+        if let (Some(a), Some(b)) = (a, b) {
+            self.clients.swap(a, b);
+        }
+        self.arrange()
+    }
+
+    /// Set whether a client needs attention, and update its `_NET_WM_STATE` to match.
+    ///
+    /// This doesn't change the client's border.
+    fn set_urgent(&mut self, window: xcb::Window, urgent: bool) -> NerdResult<()> {
+        let client = match self.position(window) {
+            Some(p) => &mut self.clients[p],
+            None => return Ok(()),
+        };
+        if client.is_urgent() == urgent {
+            return Ok(());
+        }
+
+        client.set_urgent(urgent);
+        self.ewmh_mgr.set_states(
+            client.get_window(),
+            &[ewmh::protocols::_NET_WM_STATE_DEMANDS_ATTENTION],
+            urgent,
+        )
+    }
+
+    /// Set the border, frame background and opacity of every client, depending
+    /// on whether it is focused or needs attention. Clients whose border width
+    /// changed are told how large their frame ended up.
+    ///
+    /// Clients that need attention get the urgent border even if they are focused.
+    fn update_borders(&mut self) -> NerdResult<()> {
+        let focused = *self.config.get_border_focused();
+        let unfocused = *self.config.get_border_unfocused();
+        let urgent = *self.config.get_border_urgent();
+
+        for client in self.clients.iter_mut() {
+            let is_focused = Some(client.get_window()) == self.focused;
+            let border = if client.is_urgent() {
+                &urgent
+            } else if is_focused {
+                &focused
+            } else {
+                &unfocused
+            };
+            let (background, opacity) = if is_focused {
+                (
+                    self.config.get_frame_background(),
+                    self.config.get_focused_opacity(),
+                )
+            } else {
+                (
+                    self.config.get_frame_background_unfocused(),
+                    self.config.get_unfocused_opacity(),
+                )
+            };
+            if client.set_border(&self.conn, border)? {
+                let (left, right, top, bottom) = client.get_frame_extents();
+                self.ewmh_mgr
+                    .set_frame_extents(client.get_window(), left, right, top, bottom)?;
+            }
+            client.set_background(&self.conn, background)?;
+            // Only clients gaining or losing focus change opacity.
+            if client.get_opacity() != opacity {
+                self.ewmh_mgr.set_opacity(client.get_frame(), opacity)?;
+                client.set_opacity(opacity);
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply focus to the focused client.
+    ///
+    /// If the focused client is gone, the client on top of the stack is focused instead.
+    /// This clears the focused client's need for attention, updates the borders
+    /// of all clients, raises the focused client, and gives it input focus.
+    fn update_focus(&mut self) -> NerdResult<()> {
+        if self.get_focused().is_none() {
+            self.focused = self.clients.first().map(|c| c.get_window());
         }
 
-        // Make sure the window is visible.
-        xcb::map_window_checked(&self.conn, client).request_check()?;
-        self.ewmh_mgr.update_active_window(Some(client))?;
-        self.ewmh_mgr.update_client_list(&self.clients[..])?;
+        if let Some(window) = self.focused {
+            self.set_urgent(window, false)?;
+            if self.history_cycle.is_none() {
+                self.focus_history.retain(|w| w != &window);
+                self.focus_history.insert(0, window);
+            }
+        }
+        self.update_borders()?;
+
+        match self.get_focused() {
+            Some(client) => {
+                self.layouts[self.layout].focus(client.get_window());
+                client.set_input_focus(&self.conn)?;
+                self.ewmh_mgr
+                    .update_active_window(Some(client.get_window()))?;
+            }
+            None => self.ewmh_mgr.update_active_window(None)?,
+        }
+
+        self.restack()?;
+        self.redraw_layout()
+    }
+
+    /// Restack clients, so that the focused client is above the other clients
+    /// in its level, and every level stays above the levels below it.
+    /// Dialogs belonging to the focused client are kept above it.
+    ///
+    /// The order of other clients within a level is left alone.
+    fn restack(&self) -> NerdResult<()> {
+        self.restacked.set(true);
+        let focused = self.get_focused();
+        if let Some(client) = focused {
+            client.raise(&self.conn)?;
+            for transient in self.get_transients(client.get_window()) {
+                transient.raise(&self.conn)?;
+            }
+        }
+
+        for client in self
+            .clients
+            .iter()
+            .filter(|c| c.get_level() == StackLevel::Above && Some(*c) != focused)
+        {
+            client.raise(&self.conn)?;
+        }
+        if let Some(client) = focused.filter(|c| c.get_level() == StackLevel::Above) {
+            client.raise(&self.conn)?;
+        }
+
+        // Lowering puts a client below the others, so the focused client goes first.
+        if let Some(client) = focused.filter(|c| c.get_level() == StackLevel::Below) {
+            client.lower(&self.conn)?;
+        }
+        for client in self
+            .clients
+            .iter()
+            .filter(|c| c.get_level() == StackLevel::Below && Some(*c) != focused)
+        {
+            client.lower(&self.conn)?;
+        }
         Ok(())
     }
 
+    /// Check if clients were restacked since this was last called.
+    pub fn take_restacked(&self) -> bool {
+        self.restacked.replace(false)
+    }
+
+    /// Change the stacking level of a client, and restack clients to match.
+    ///
+    /// The client's `_NET_WM_STATE` is updated to match.
+    pub fn set_level(&mut self, window: xcb::Window, level: StackLevel) -> NerdResult<()> {
+        let p = match self.position(window) {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+
+        self.clients[p].set_level(level);
+        let window = self.clients[p].get_window();
+        self.ewmh_mgr.set_states(
+            window,
+            &[ewmh::protocols::_NET_WM_STATE_ABOVE],
+            level == StackLevel::Above,
+        )?;
+        self.ewmh_mgr.set_states(
+            window,
+            &[ewmh::protocols::_NET_WM_STATE_BELOW],
+            level == StackLevel::Below,
+        )?;
+        self.restack()
+    }
+
+    /// Toggle between keeping a client above all other clients, and the normal level.
+    pub fn toggle_above(&mut self, window: xcb::Window) -> NerdResult<()> {
+        let level = match self.position(window).map(|p| self.clients[p].get_level()) {
+            Some(StackLevel::Above) => StackLevel::Normal,
+            Some(_) => StackLevel::Above,
+            None => return Ok(()),
+        };
+        self.set_level(window, level)
+    }
+
+    /// Apply a client's request to change its `_NET_WM_STATE`.
+    ///
+    /// `action` is one of the [`ewmh::state_action`]s, and `states` are the
+    /// atoms of the states to change. Unsupported states are ignored.
+    /// Returns `false` if the window isn't managed by this desktop.
+    pub fn request_state(
+        &mut self,
+        window: xcb::Window,
+        action: u32,
+        states: &[xcb::Atom],
+    ) -> NerdResult<bool> {
+        let p = match self.position(window) {
+            Some(p) if self.clients[p].get_window() == window => p,
+            _ => return Ok(false),
+        };
+
+        let apply = |current: bool| match action {
+            ewmh::state_action::REMOVE => false,
+            ewmh::state_action::ADD => true,
+            ewmh::state_action::TOGGLE => !current,
+            _ => current,
+        };
+
+        let above = self
+            .ewmh_mgr
+            .get_atom(ewmh::protocols::_NET_WM_STATE_ABOVE)?;
+        let below = self
+            .ewmh_mgr
+            .get_atom(ewmh::protocols::_NET_WM_STATE_BELOW)?;
+        let demands_attention = self
+            .ewmh_mgr
+            .get_atom(ewmh::protocols::_NET_WM_STATE_DEMANDS_ATTENTION)?;
+        let maximized = [
+            self.ewmh_mgr
+                .get_atom(ewmh::protocols::_NET_WM_STATE_MAXIMIZED_VERT)?,
+            self.ewmh_mgr
+                .get_atom(ewmh::protocols::_NET_WM_STATE_MAXIMIZED_HORZ)?,
+        ];
+
+        let level = self.clients[p].get_level();
+        if states.contains(&above) {
+            let level = match (apply(level == StackLevel::Above), level) {
+                (true, _) => StackLevel::Above,
+                (false, StackLevel::Above) => StackLevel::Normal,
+                (false, level) => level,
+            };
+            self.set_level(window, level)?;
+        } else if states.contains(&below) {
+            let level = match (apply(level == StackLevel::Below), level) {
+                (true, _) => StackLevel::Below,
+                (false, StackLevel::Below) => StackLevel::Normal,
+                (false, level) => level,
+            };
+            self.set_level(window, level)?;
+        }
+
+        if states.contains(&demands_attention) {
+            let urgent = apply(self.clients[p].is_urgent()) && self.focused != Some(window);
+            self.set_urgent(window, urgent)?;
+            self.update_borders()?;
+        }
+
+        // Both maximized states are usually changed together, and are
+        // treated as one.
+        if states.iter().any(|s| maximized.contains(s)) {
+            let current = self.is_maximized(window);
+            if apply(current) != current {
+                self.toggle_maximized(window)?;
+            }
+        }
+        Ok(true)
+    }
+
     /// Remove a window from the stack, and unmap it.
-    pub fn remove(&mut self, client: xcb::Window) -> NerdResult<()> {
-        if let Some(p) = self.clients.iter().position(|c| c == &client) {
-            self.clients.remove(p);
-            self.layout_mgr.configure(&self.clients)?;
+    pub fn remove(&mut self, window: xcb::Window) -> NerdResult<()> {
+        if let Some(p) = self.position(window) {
+            let client = self.clients.remove(p);
+            self.unfocus(client.get_window(), p);
+            self.floating.retain(|c| c != &client.get_window());
+            self.snapped.retain(|s| s.window != client.get_window());
+            self.transients
+                .retain(|(c, p)| c != &client.get_window() && p != &client.get_window());
+            if self.is_maximized(client.get_window()) {
+                self.maximized.retain(|(c, _)| c != &client.get_window());
+                self.ewmh_mgr.set_maximized(client.get_window(), false)?;
+            }
+            // Hide the window.
+            client.unmap(&self.conn)?;
+            self.update_focus()?;
+            self.arrange()?;
+        }
+        Ok(())
+    }
+
+    /// Move the focus off `window`, which was just removed from `position` in the stack.
+    ///
+    /// The client that was above it in the stack takes the focus, or the new
+    /// top of the stack if it was on top. Nothing changes if it wasn't focused.
+    fn unfocus(&mut self, window: xcb::Window, position: usize) {
+        if self.focused == Some(window) {
+            self.focused = self
+                .clients
+                .get(position.saturating_sub(1))
+                .map(|c| c.get_window());
+        }
+    }
+
+    /// Stop managing a client window, and destroy its frame.
+    ///
+    /// Pass `destroyed` if the client window no longer exists.
+    /// Returns `false` if the window isn't managed by this desktop.
+    pub fn unmanage(&mut self, window: xcb::Window, destroyed: bool) -> NerdResult<bool> {
+        let p = match self.position(window) {
+            Some(p) if self.clients[p].get_window() == window => p,
+            _ => return Ok(false),
+        };
+
+        let client = self.clients.remove(p);
+        self.unfocus(window, p);
+        self.floating.retain(|c| c != &window);
+        self.snapped.retain(|s| s.window != window);
+        self.transients
+            .retain(|(c, p)| c != &window && p != &window);
+        self.closing.retain(|(w, _)| w != &window);
+        self.focus_history.retain(|w| w != &window);
+        self.history_cycle = None;
+        if self.is_maximized(window) {
+            self.maximized.retain(|(c, _)| c != &window);
+            if !destroyed {
+                self.ewmh_mgr.set_maximized(window, false)?;
+            }
+        }
+
+        // Stop dragging the client.
+        if self.moving.map(|(w, _)| w) == Some(window) {
+            self.moving = None;
+        }
+        if self.resizing.map(|(w, _, _)| w) == Some(window) {
+            self.resizing = None;
+        }
+        client.release(&self.conn, destroyed)?;
+
+        self.update_focus()?;
+        self.arrange()?;
+        Ok(true)
+    }
+
+    /// Ask the focused client to close. See [`Desktop::close`].
+    pub fn close_focused(&mut self) -> NerdResult<()> {
+        match self.get_focused().map(ClientWindow::get_window) {
+            Some(window) => self.close(window),
+            None => Ok(()),
+        }
+    }
+
+    /// Close a client, given any of its windows.
+    ///
+    /// Clients supporting `WM_DELETE_WINDOW` are sent a message asking them
+    /// to close themselves, and are killed if they are still around after the
+    /// configured timeout. Any other client is killed right away.
+    pub fn close(&mut self, window: xcb::Window) -> NerdResult<()> {
+        let client = match self.position(window) {
+            Some(p) => self.clients[p].get_window(),
+            None => return Ok(()),
+        };
+
+        if self
+            .ewmh_mgr
+            .has_protocol(client, ewmh::icccm::WM_DELETE_WINDOW)?
+        {
+            let wm_protocols = self.ewmh_mgr.get_atom(ewmh::icccm::WM_PROTOCOLS)?;
+            let wm_delete_window = self.ewmh_mgr.get_atom(ewmh::icccm::WM_DELETE_WINDOW)?;
+            let event = xcb::ClientMessageEvent::new(
+                32,
+                client,
+                wm_protocols,
+                xcb::ClientMessageData::from_data32([wm_delete_window, xcb::CURRENT_TIME, 0, 0, 0]),
+            );
+            xcb::send_event_checked(&self.conn, false, client, xcb::EVENT_MASK_NO_EVENT, &event)
+                .request_check()?;
+
+            if !self.closing.iter().any(|(w, _)| w == &client) {
+                let deadline = Instant::now() + self.config.get_close_timeout();
+                self.closing.push((client, deadline));
+            }
+        } else {
+            xcb::kill_client_checked(&self.conn, client).request_check()?;
         }
 
-        // Hide the window.
-        xcb::unmap_window_checked(&self.conn, client).request_check()?;
-        self.ewmh_mgr.update_client_list(&self.clients[..])?;
+        Ok(())
+    }
+
+    /// Get when the next client that was asked to close should be killed, if any.
+    pub fn get_next_kill(&self) -> Option<Instant> {
+        self.closing.iter().map(|(_, deadline)| *deadline).min()
+    }
+
+    /// Kill the clients that haven't closed themselves in time.
+    pub fn kill_unresponsive(&mut self) -> NerdResult<()> {
+        let now = Instant::now();
+        let (expired, closing) = self
+            .closing
+            .drain(..)
+            .partition(|(_, deadline)| *deadline <= now);
+        self.closing = closing;
+
+        for (window, _) in expired {
+            info!("Killing unresponsive client {}", window);
+            let result = xcb::kill_client_checked(&self.conn, window)
+                .request_check()
+                .map_err(Error::from);
+            match result {
+                // The client closed just in time.
+                Err(e) if e.is_bad_window() => {}
+                res => res?,
+            }
+        }
         Ok(())
     }
 
     /// Show all the clients owned by this desktop.
     pub fn show(&mut self) -> NerdResult<()> {
         for client in self.clients.iter().rev() {
-            xcb::map_window_checked(&self.conn, *client).request_check()?;
+            client.map(&self.conn)?;
         }
-        self.ewmh_mgr.update_client_list(&self.clients[..])?;
+        self.update_focus()?;
+        self.arrange()
+    }
+
+    /// Get when the animations should next be stepped, if any are running.
+    pub fn get_next_frame(&self) -> Option<Instant> {
+        if self.animations.is_empty() {
+            None
+        } else {
+            Some(self.last_frame + animation::FRAME)
+        }
+    }
+
+    /// Move animated clients one step closer to their place, and finish the
+    /// animations that are over.
+    pub fn step_animations(&mut self) -> NerdResult<()> {
+        let now = Instant::now();
+        self.last_frame = now;
+
+        let mut running = vec![];
+        for animation in self.animations.drain(..) {
+            match animation.get_geometry(now) {
+                (geometry, true) => place(&self.conn, animation.get_client(), geometry)?,
+                (geometry, false) => {
+                    animation.get_client().drag_configure(&self.conn, geometry);
+                    running.push(animation);
+                }
+            }
+        }
+        self.animations = running;
         Ok(())
     }
 
     /// Hide all the clients owned by this desktop.
-    pub fn hide(&self) -> NerdResult<()> {
+    ///
+    /// Animations are finished right away.
+    pub fn hide(&mut self) -> NerdResult<()> {
+        for animation in self.animations.drain(..) {
+            place(
+                &self.conn,
+                animation.get_client(),
+                animation.get_geometry(Instant::now()).0,
+            )?;
+        }
         for client in self.clients.iter().rev() {
-            xcb::unmap_window_checked(&self.conn, *client).request_check()?;
+            client.unmap(&self.conn)?;
+        }
+        self.layouts[self.layout].hide(&self.conn)?;
+        Ok(())
+    }
+
+    /// Take every client off this desktop, keeping their frames, so they can be
+    /// given to another desktop with [`Desktop::give_clients`].
+    ///
+    /// The desktop should be hidden first. Each client is returned along with
+    /// whether it was floating.
+    pub fn take_clients(&mut self) -> NerdResult<Vec<(ClientWindow, bool)>> {
+        for (window, _) in self.maximized.drain(..) {
+            self.ewmh_mgr.set_maximized(window, false)?;
+        }
+        self.animations.clear();
+        self.focused = None;
+        self.focus_history.clear();
+        self.history_cycle = None;
+        self.snapped.clear();
+        self.transients.clear();
+        self.closing.clear();
+        self.placed.clear();
+        self.previous_master = None;
+        self.moving = None;
+        self.resizing = None;
+
+        let floating = std::mem::take(&mut self.floating);
+        Ok(self
+            .clients
+            .take_all()
+            .into_iter()
+            .map(|c| {
+                let is_floating = floating.contains(&c.get_window());
+                (c, is_floating)
+            })
+            .collect())
+    }
+
+    /// Take a single client off this desktop, keeping its frame mapped, so it
+    /// can be given to another desktop with [`Desktop::give_clients`].
+    ///
+    /// Returns the client along with whether it was floating, or [`None`] if
+    /// the window isn't managed by this desktop.
+    pub fn take_client(&mut self, window: xcb::Window) -> NerdResult<Option<(ClientWindow, bool)>> {
+        let p = match self.position(window) {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+
+        let client = self.clients.remove(p);
+        let window = client.get_window();
+        self.unfocus(window, p);
+        let floating = self.is_floating(window);
+        self.floating.retain(|c| c != &window);
+        self.snapped.retain(|s| s.window != window);
+        self.transients
+            .retain(|(c, p)| c != &window && p != &window);
+        self.closing.retain(|(w, _)| w != &window);
+        self.focus_history.retain(|w| w != &window);
+        self.history_cycle = None;
+        self.placed.remove(&window);
+        self.animations
+            .retain(|a| a.get_client().get_window() != window);
+        if self.is_maximized(window) {
+            self.maximized.retain(|(c, _)| c != &window);
+            self.ewmh_mgr.set_maximized(window, false)?;
+        }
+
+        self.update_focus()?;
+        self.arrange()?;
+        Ok(Some((client, floating)))
+    }
+
+    /// Add clients taken from another desktop to the bottom of the stack.
+    ///
+    /// The clients are mapped if this desktop is `visible`.
+    pub fn give_clients(
+        &mut self,
+        clients: Vec<(ClientWindow, bool)>,
+        visible: bool,
+    ) -> NerdResult<()> {
+        for (client, floating) in clients {
+            if floating {
+                self.floating.push(client.get_window());
+            }
+            // Clients are no longer maximized once taken from their desktop.
+            self.ewmh_mgr
+                .set_allowed_actions(client.get_window(), floating)?;
+            if visible {
+                client.map(&self.conn)?;
+            }
+            self.clients.push(client);
+        }
+
+        if visible {
+            self.update_focus()?;
+            self.arrange()?;
+        }
+        Ok(())
+    }
+
+    /// Stop managing every client, giving the windows back to the root window.
+    ///
+    /// Used when the window manager exits, so clients are left where they were,
+    /// and when the desktop is removed. Anything drawn by the layouts is
+    /// destroyed, and only a blank layout is left.
+    pub fn release_all(&mut self) -> NerdResult<()> {
+        self.animations.clear();
+        self.focused = None;
+        self.focus_history.clear();
+        self.history_cycle = None;
+        self.floating.clear();
+        self.maximized.clear();
+        self.snapped.clear();
+        self.transients.clear();
+        self.closing.clear();
+        self.placed.clear();
+        self.previous_master = None;
+        self.moving = None;
+        self.resizing = None;
+        for client in self.clients.take_all() {
+            client.release(&self.conn, false)?;
+        }
+        for layout in self.layouts.drain(..) {
+            layout.destroy(&self.conn)?;
         }
-        self.ewmh_mgr.update_client_list(&[])?;
+        self.layouts.push(Box::new(layout::BlankLayout::default()));
+        self.layout = 0;
         Ok(())
     }
 
     /// Execute an action, and reconfigure the layout.
+    ///
+    /// Actions that already reconfigure the layout return early, so it is
+    /// only arranged once.
     pub fn do_action(&mut self, action: Action) -> NerdResult<()> {
         match action.get_type() {
-            ActionType::FloatingWindowMove => {
-                self.move_handler(action.get_event())?;
+            ActionType::FloatingWindowMove | ActionType::FloatingWindowResize => {
+                if action.get_type() == ActionType::FloatingWindowMove {
+                    self.move_handler(action.get_event())?;
+                } else {
+                    self.resize_handler(action.get_event())?;
+                }
+                // Drags only touch the dragged window, so the layout is only
+                // rearranged once they end, rather than on every motion event.
+                if !matches!(action.get_event(), Event::ButtonRelease(_)) {
+                    return Ok(());
+                }
             }
             ActionType::WindowFocus => {
                 self.focus_handler(action.get_event())?;
             }
+            ActionType::WindowClose => match self.get_target(action.get_event()) {
+                Some(window) => self.close(window)?,
+                None => self.close_focused()?,
+            },
+            ActionType::ToggleMaximize => {
+                let target = self.get_target(action.get_event()).or(self.focused);
+                if let Some(window) = target {
+                    self.toggle_maximized(window)?;
+                }
+                return Ok(());
+            }
+            ActionType::SnapLeft
+            | ActionType::SnapRight
+            | ActionType::SnapTop
+            | ActionType::SnapBottom
+            | ActionType::SnapMaximize => {
+                let edge = match action.get_type() {
+                    ActionType::SnapLeft => SnapEdge::Left,
+                    ActionType::SnapRight => SnapEdge::Right,
+                    ActionType::SnapTop => SnapEdge::Top,
+                    ActionType::SnapBottom => SnapEdge::Bottom,
+                    _ => SnapEdge::Full,
+                };
+                if let Some(window) = self.focused {
+                    self.snap(window, edge)?;
+                }
+            }
+            ActionType::Unsnap => {
+                if let Some(window) = self.focused {
+                    self.unsnap(window)?;
+                }
+            }
+            ActionType::FocusNext => {
+                self.focus_next()?;
+            }
+            ActionType::FocusPrev => {
+                self.focus_prev()?;
+            }
+            ActionType::MoveStackUp => {
+                self.move_stack_up()?;
+            }
+            ActionType::MoveStackDown => {
+                self.move_stack_down()?;
+            }
+            ActionType::IncMasterRatio => {
+                return self.adjust_master(self.config.get_master_ratio_step());
+            }
+            ActionType::DecMasterRatio => {
+                return self.adjust_master(-self.config.get_master_ratio_step());
+            }
+            ActionType::SwapMaster => {
+                return self.swap_master();
+            }
+            ActionType::ToggleFloating => {
+                if let Some(window) = self.focused {
+                    self.toggle_floating(window)?;
+                }
+                return Ok(());
+            }
+            ActionType::ToggleAbove => {
+                let target = self.get_target(action.get_event()).or(self.focused);
+                if let Some(window) = target {
+                    self.toggle_above(window)?;
+                }
+            }
+            ActionType::RotateSplit => {
+                return self.rotate_split();
+            }
+            ActionType::FlipSplit => {
+                return self.flip_split();
+            }
+            ActionType::CycleLayout => {
+                return self.cycle_layout();
+            }
             _ => {}
         }
-        self.layout_mgr.configure(&self.clients[..])?;
-        Ok(())
+        self.arrange()
+    }
+
+    /// Get the client an event was triggered on, if it is managed by this desktop.
+    fn get_target(&self, event: &Event) -> Option<xcb::Window> {
+        let window = match event {
+            Event::ButtonPress(e) => match e.child() {
+                0 => e.event(),
+                child => child,
+            },
+            _ => return None,
+        };
+        self.position(window).map(|p| self.clients[p].get_window())
     }
 
     /// Internal handler for setting the focus on clients.
     ///
     /// This handler works on the following events:
     ///  - [`Event::WindowMapRequest`]
-    ///     Map a window and set the focus on it.
+    ///    Map a window and set the focus on it, unless it shouldn't take
+    ///    the focus. See [`Desktop::wants_focus`].
     ///  - [`Event::ButtonPress`]
-    ///     Sets the focus on the window the button was pressed on.
+    ///    Sets the focus on the window the button was pressed on.
     fn focus_handler(&mut self, event: &Event) -> NerdResult<()> {
         match event {
             Event::WindowMapRequest(e) => {
-                xcb::map_window_checked(&self.conn, e.window()).request_check()?;
-                self.focus(e.window())?;
+                if self.wants_focus(e.window())? {
+                    self.manage(e.window())?;
+                } else {
+                    trace!("Mapping {} without focusing it", e.window());
+                    self.manage_unfocused(e.window())?;
+                }
             }
             Event::ButtonPress(e) => {
-                // Child doesn't exist
-                if e.child() == 0 {
+                // Clicking a tab focuses its client.
+                if let Some(window) = self.get_layout_client(e.event(), e.event_x(), e.event_y()) {
+                    return self.focus(window);
+                }
+
+                // Presses on a titlebar are reported on the titlebar itself.
+                let window = match e.child() {
+                    0 => e.event(),
+                    child => child,
+                };
+                if is_override_redirect(&self.conn, window)? {
+                    trace!("Ignoring override-redirect window {}", window);
                     return Ok(());
                 }
-                self.focus(e.child())?;
+                self.focus(window)?;
             }
             _ => {}
         }
@@ -154,71 +1907,344 @@ impl Desktop {
         Ok(())
     }
 
+    /// Get the window a button press should start dragging.
+    ///
+    /// The press is reported on the grab window, which is either the root window,
+    /// with the window under the pointer as its child, or a client's titlebar.
+    /// Managed clients are identified by their client window.
+    fn get_drag_target(&self, e: &xcb::ButtonPressEvent) -> Option<xcb::Window> {
+        let window = match (self.position(e.event()), e.child()) {
+            (Some(_), _) => e.event(),
+            (None, 0) => return None,
+            (None, child) => child,
+        };
+
+        match self.position(window) {
+            Some(p) => Some(self.clients[p].get_window()),
+            None => Some(window),
+        }
+    }
+
+    /// Get the window that should actually be configured when dragging `window`.
+    ///
+    /// Managed clients are moved by their frame.
+    fn get_drag_window(&self, window: xcb::Window) -> xcb::Window {
+        match self.position(window) {
+            Some(p) => self.clients[p].get_frame(),
+            None => window,
+        }
+    }
+
+    /// Make a tiled client floating before it is dragged, so the layout doesn't
+    /// put it back in its tile. It stays where the layout last put it.
+    fn float_for_drag(&mut self, window: xcb::Window) -> NerdResult<()> {
+        if self.contains(window) && !self.is_floating(window) {
+            trace!("Floating {} to drag it", window);
+            self.set_floating(window, true)?;
+        }
+        Ok(())
+    }
+
+    /// Actively grab the pointer for the rest of a drag, showing the cursor named `cursor`.
+    ///
+    /// All pointer events are then reported to the window manager, even if the
+    /// pointer leaves the window being dragged. The pointer is kept inside
+    /// `confine_to`, unless it is [`xcb::NONE`], so fast drags can't lose it
+    /// off the screen. The grab is released by [`Desktop::ungrab_pointer`],
+    /// which also restores the cursor.
+    fn grab_pointer(
+        &self,
+        e: &xcb::ButtonPressEvent,
+        cursor: &str,
+        confine_to: xcb::Window,
+    ) -> NerdResult<()> {
+        let cursor = cursor::get_cursor_named(&self.conn, cursor).unwrap_or_else(|err| {
+            warn!("Unable to create cursor {:?}: {}", cursor, err);
+            xcb::NONE
+        });
+
+        let reply = xcb::grab_pointer(
+            &self.conn,
+            false,
+            e.root(),
+            (xcb::EVENT_MASK_BUTTON_RELEASE
+                | xcb::EVENT_MASK_BUTTON_MOTION
+                | xcb::EVENT_MASK_POINTER_MOTION) as u16,
+            xcb::GRAB_MODE_ASYNC as u8,
+            xcb::GRAB_MODE_ASYNC as u8,
+            confine_to,
+            cursor,
+            e.time(),
+        )
+        .get_reply();
+
+        // The grab keeps its own reference to the cursor.
+        if cursor != xcb::NONE {
+            xcb::free_cursor(&self.conn, cursor);
+        }
+
+        let status = reply?.status();
+        if status != xcb::GRAB_STATUS_SUCCESS as u8 {
+            warn!("Unable to grab pointer (status {})", status);
+        }
+        Ok(())
+    }
+
+    /// Release a pointer grab taken by [`Desktop::grab_pointer`].
+    fn ungrab_pointer(&self) {
+        xcb::ungrab_pointer(&self.conn, xcb::CURRENT_TIME);
+        self.conn.flush();
+    }
+
+    /// Stop moving or resizing, and release the pointer.
+    ///
+    /// This is safe to call if the dragged window was destroyed during the
+    /// drag, as the grab belongs to the root window.
+    fn end_drag(&mut self) {
+        trace!("Ending drag");
+        self.last_mouse = None;
+        self.moving = None;
+        self.snap_targets.clear();
+        self.resizing = None;
+        self.ungrab_pointer();
+    }
+
+    /// Get the window being moved, and where it should be with the pointer at `(x, y)`.
+    ///
+    /// Edges of the window within the configured threshold of an edge of the
+    /// desktop or another client are snapped to it.
+    fn get_move_position(&self, x: i16, y: i16) -> Option<(xcb::Window, (i16, i16))> {
+        let (start, (window, geometry)) = (self.last_mouse?, self.moving?);
+        let moved = Rectangle {
+            x: geometry.x + (x - start.0),
+            y: geometry.y + (y - start.1),
+            ..geometry
+        };
+
+        let threshold = self.config.get_snap_threshold() as i32;
+        if threshold == 0 {
+            return Some((window, (moved.x, moved.y)));
+        }
+
+        // Other clients are only snapped to if they are side by side with the window.
+        let overlaps = |start: i16, length: u16, other_start: i16, other_length: u16| {
+            (start as i32) < other_start as i32 + other_length as i32
+                && (other_start as i32) < start as i32 + length as i32
+        };
+        let targets = || self.snap_targets.iter().enumerate();
+        let x = snap_edge(
+            moved.x,
+            moved.width,
+            targets()
+                .filter(|&(i, t)| i == 0 || overlaps(moved.y, moved.height, t.y, t.height))
+                .flat_map(|(_, t)| [t.x, t.x + t.width as i16]),
+            threshold,
+        );
+        let y = snap_edge(
+            moved.y,
+            moved.height,
+            targets()
+                .filter(|&(i, t)| i == 0 || overlaps(moved.x, moved.width, t.x, t.width))
+                .flat_map(|(_, t)| [t.y, t.y + t.height as i16]),
+            threshold,
+        );
+        Some((window, (x, y)))
+    }
+
+    /// Get the window being resized, and its geometry with the pointer at `(x, y)`.
+    fn get_resize_geometry(&self, x: i16, y: i16) -> Option<(xcb::Window, Rectangle)> {
+        /// Smallest width and height a client can be resized to.
+        const MIN_SIZE: u16 = 32;
+
+        let (start, (window, grip, geometry)) = (self.last_mouse?, self.resizing?);
+        let delta = (x - start.0, y - start.1);
+        Some((window, grip.resize(geometry, delta, MIN_SIZE)))
+    }
+
     /// Internal handler for moving windows.
     ///
     /// This handler works on the following events:
     ///  - [`Event::ButtonPress`]
-    ///     Sets the focus on a client and starts starts keeping track of the
-    ///     pointer position.
+    ///    Sets the focus on a client, makes it floating, grabs the pointer
+    ///    and starts keeping track of the pointer position.
     ///  - [`Event::PointerMotion`]
-    ///     All [`Event::PointerMotion`] events are handled after the Button associated to
-    ///     the action is pressed.
-    /// - [`Event::ButtonRelease`]
-    ///     Stops handling [`Event::PointerMotion`] events after the Button associated to
-    ///     the action is released.
+    ///    All [`Event::PointerMotion`] events are handled after the Button associated to
+    ///    the action is pressed.
+    ///  - [`Event::ButtonRelease`]
+    ///    Moves the window to its final position, releases the pointer, and stops handling
+    ///    [`Event::PointerMotion`] events after the Button associated to
+    ///    the action is released.
     fn move_handler(&mut self, event: &Event) -> NerdResult<()> {
         // Make sure the client is focused
         self.focus_handler(event)?;
 
         match event {
-            // Move window by pointer delta
+            // Move window by the distance the pointer moved since the drag started.
+            // Positions are never read back while dragging, so motion events
+            // that arrive late or out of step can't accumulate errors, and
+            // there is no round trip to the server until the drag ends.
             Event::PointerMotion(e) => {
-                // Child doesn't exist
-                if e.child() == 0 {
-                    return Ok(());
+                if let Some((window, (x, y))) = self.get_move_position(e.root_x(), e.root_y()) {
+                    trace!("Moving {} to ({}, {})", window, x, y);
+
+                    match self.position(window) {
+                        Some(p) => self.clients[p].drag_to(&self.conn, x, y),
+                        None => {
+                            xcb::configure_window(
+                                &self.conn,
+                                window,
+                                &[
+                                    (xcb::CONFIG_WINDOW_X as u16, x as u32),
+                                    (xcb::CONFIG_WINDOW_Y as u16, y as u32),
+                                ],
+                            );
+                            self.conn.flush();
+                        }
+                    }
                 }
+            }
+            Event::ButtonPress(e) => {
+                // Remember the window the drag started on, since the pointer
+                // may leave it between motion events.
+                let window = match self.get_drag_target(e) {
+                    Some(w) => w,
+                    None => return Ok(()),
+                };
+                self.float_for_drag(window)?;
 
-                if let Some(last_mouse) = self.last_mouse {
-                    // WHY do we get negative values for position?
-                    let properties = xcb::get_geometry(&self.conn, e.child()).get_reply()?;
+                // Positions are relative to the parent, which is what
+                // `configure_window` expects. Frames are children of the root
+                // window, so this matches the root coordinates of the pointer.
+                let drag_window = self.get_drag_window(window);
+                let properties = WindowProperties::query(&self.conn, drag_window)?;
+                self.moving = Some((window, properties.get_outer_geometry()));
 
-                    trace!(
-                        "\nOld X: {} Old Y: {}\nNew X: {} New Y: {}\nLast Mouse: {:?}",
-                        properties.x(),
-                        properties.y(),
-                        (properties.x() + (e.root_x() - last_mouse.0)),
-                        (properties.y() + (e.root_y() - last_mouse.1)),
-                        self.last_mouse
-                    );
+                // The desktop comes first, since it is snapped to along its whole length.
+                self.snap_targets = vec![self.area];
+                for client in self.clients.iter() {
+                    if client.get_frame() != drag_window {
+                        self.snap_targets.push(client.get_geometry(&self.conn)?);
+                    }
+                }
+                self.last_mouse = Some((e.root_x(), e.root_y()));
+                self.grab_pointer(e, "fleur", e.root())?;
+            }
+            Event::ButtonRelease(e) => {
+                // Settle the window where the pointer was released, and
+                // tell the client where it ended up.
+                let result = match self.get_move_position(e.root_x(), e.root_y()) {
+                    Some((window, (x, y))) => match self.position(window) {
+                        Some(p) => self.clients[p].move_to(&self.conn, x, y),
+                        None => xcb::configure_window_checked(
+                            &self.conn,
+                            window,
+                            &[
+                                (xcb::CONFIG_WINDOW_X as u16, x as u32),
+                                (xcb::CONFIG_WINDOW_Y as u16, y as u32),
+                            ],
+                        )
+                        .request_check()
+                        .map_err(Error::from),
+                    },
+                    None => Ok(()),
+                };
+
+                // The drag is over even if the window went away during it.
+                self.end_drag();
+                result?;
+            }
+            _ => {}
+        }
 
-                    let changes: [(u16, u32); 2] = [
-                        (
-                            xcb::CONFIG_WINDOW_X as u16,
-                            (properties.x() + (e.root_x() - last_mouse.0)) as u32,
-                        ),
-                        (
-                            xcb::CONFIG_WINDOW_Y as u16,
-                            (properties.y() + (e.root_y() - last_mouse.1)) as u32,
-                        ),
-                    ];
+        Ok(())
+    }
 
-                    xcb::configure_window_checked(&self.conn, e.child(), &changes)
-                        .request_check()?;
+    /// Internal handler for resizing windows.
+    ///
+    /// This handler works on the following events:
+    ///  - [`Event::ButtonPress`]
+    ///    Sets the focus on a client, makes it floating, grabs the pointer,
+    ///    and records the pointer position and the client's geometry.
+    ///  - [`Event::PointerMotion`]
+    ///    Resizes the client by the distance the pointer moved since the
+    ///    button was pressed. The edge or corner nearest to where the button
+    ///    was pressed is dragged, and the opposite one stays in place.
+    ///  - [`Event::ButtonRelease`]
+    ///    Resizes the client to its final size, releases the pointer, and stops resizing it.
+    fn resize_handler(&mut self, event: &Event) -> NerdResult<()> {
+        // Make sure the client is focused
+        self.focus_handler(event)?;
 
-                    self.last_mouse = Some((e.root_x(), e.root_y()));
+        match event {
+            // Like moving, geometry is never read back while resizing.
+            Event::PointerMotion(e) => {
+                if let Some((window, geometry)) = self.get_resize_geometry(e.root_x(), e.root_y()) {
+                    match self.position(window) {
+                        Some(p) => self.clients[p].drag_configure(&self.conn, geometry),
+                        None => {
+                            xcb::configure_window(
+                                &self.conn,
+                                window,
+                                &[
+                                    (xcb::CONFIG_WINDOW_X as u16, geometry.x as u32),
+                                    (xcb::CONFIG_WINDOW_Y as u16, geometry.y as u32),
+                                    (xcb::CONFIG_WINDOW_WIDTH as u16, geometry.width as u32),
+                                    (xcb::CONFIG_WINDOW_HEIGHT as u16, geometry.height as u32),
+                                ],
+                            );
+                            self.conn.flush();
+                        }
+                    }
                 }
             }
             Event::ButtonPress(e) => {
-                // Child doesn't exist
-                if e.child() == 0 {
-                    return Ok(());
-                }
+                let window = match self.get_drag_target(e) {
+                    Some(w) => w,
+                    None => return Ok(()),
+                };
+                self.float_for_drag(window)?;
+
+                let geometry = match self.position(window) {
+                    Some(p) => self.clients[p].get_geometry(&self.conn)?,
+                    // Unmanaged windows are resized directly, which excludes their border.
+                    None => {
+                        let properties = WindowProperties::query(&self.conn, window)?;
+                        let ((x, y), (width, height)) =
+                            (properties.get_position(), properties.get_size());
+                        Rectangle::new(x, y, width, height)
+                    }
+                };
+                let grip = if self.config.get_resize_from_corners() {
+                    ResizeGrip::from_quadrant(geometry, e.root_x(), e.root_y())
+                } else {
+                    ResizeGrip::from_position(geometry, e.root_x(), e.root_y())
+                };
+                self.resizing = Some((window, grip, geometry));
                 self.last_mouse = Some((e.root_x(), e.root_y()));
+                self.grab_pointer(e, grip.get_cursor(), e.root())?;
             }
-            Event::ButtonRelease(_) => {
-                // Forget last mouse position
-                info!("Forgetting last mouse position");
-                self.last_mouse = None;
+            Event::ButtonRelease(e) => {
+                let result = match self.get_resize_geometry(e.root_x(), e.root_y()) {
+                    Some((window, geometry)) => match self.position(window) {
+                        Some(p) => self.clients[p].configure(&self.conn, geometry),
+                        None => xcb::configure_window_checked(
+                            &self.conn,
+                            window,
+                            &[
+                                (xcb::CONFIG_WINDOW_X as u16, geometry.x as u32),
+                                (xcb::CONFIG_WINDOW_Y as u16, geometry.y as u32),
+                                (xcb::CONFIG_WINDOW_WIDTH as u16, geometry.width as u32),
+                                (xcb::CONFIG_WINDOW_HEIGHT as u16, geometry.height as u32),
+                            ],
+                        )
+                        .request_check()
+                        .map_err(Error::from),
+                    },
+                    None => Ok(()),
+                };
+
+                self.end_drag();
+                result?;
             }
             _ => {}
         }
@@ -4,6 +4,7 @@
 
 use std::sync::Arc;
 
+use super::geometry::{Rectangle, Strut};
 use crate::atoms::AtomManager;
 use crate::prelude::*;
 
@@ -11,12 +12,59 @@ use crate::prelude::*;
 define_string_consts! {
     pub protocols {
         _NET_SUPPORTED,
+        _NET_SUPPORTING_WM_CHECK,
         _NET_WM_NAME,
         _NET_WM_PID,
         _NET_CLIENT_LIST,
+        _NET_CLIENT_LIST_STACKING,
         _NET_DESKTOP_NAMES,
         _NET_NUMBER_OF_DESKTOPS,
+        _NET_CURRENT_DESKTOP,
         _NET_ACTIVE_WINDOW,
+        _NET_WM_WINDOW_TYPE,
+        _NET_WM_WINDOW_TYPE_DOCK,
+        _NET_WM_WINDOW_TYPE_TOOLBAR,
+        _NET_WM_WINDOW_TYPE_UTILITY,
+        _NET_WM_WINDOW_TYPE_SPLASH,
+        _NET_WM_WINDOW_TYPE_DIALOG,
+        _NET_WM_STRUT,
+        _NET_WM_STRUT_PARTIAL,
+        _NET_WORKAREA,
+        _NET_WM_STATE,
+        _NET_WM_STATE_MAXIMIZED_VERT,
+        _NET_WM_STATE_MAXIMIZED_HORZ,
+        _NET_WM_STATE_ABOVE,
+        _NET_WM_STATE_BELOW,
+        _NET_WM_STATE_DEMANDS_ATTENTION,
+        _NET_WM_WINDOW_OPACITY,
+        _NET_FRAME_EXTENTS,
+        _NET_WM_USER_TIME,
+        _NET_WM_ALLOWED_ACTIONS,
+        _NET_WM_ACTION_MOVE,
+        _NET_WM_ACTION_RESIZE,
+        _NET_WM_ACTION_MAXIMIZE_HORZ,
+        _NET_WM_ACTION_MAXIMIZE_VERT,
+        _NET_WM_ACTION_CLOSE,
+        _NET_WM_ACTION_ABOVE,
+        _NET_WM_ACTION_BELOW,
+    }
+}
+
+/// Actions in `_NET_WM_STATE` client messages.
+pub mod state_action {
+    /// Remove the state.
+    pub const REMOVE: u32 = 0;
+    /// Add the state.
+    pub const ADD: u32 = 1;
+    /// Remove the state if it is set, or add it otherwise.
+    pub const TOGGLE: u32 = 2;
+}
+
+// Atoms defined by the ICCCM
+define_string_consts! {
+    pub icccm {
+        WM_PROTOCOLS,
+        WM_DELETE_WINDOW,
     }
 }
 
@@ -25,21 +73,24 @@ define_string_consts! {
 /// Also provides general functions for managing properties / atoms.
 pub struct EWMHManager {
     conn: Arc<xcb::Connection>,
+    /// Index of the screen whose root window holds the hints.
+    screen: usize,
     atoms: AtomManager,
 }
 
 impl EWMHManager {
     #[must_use]
-    pub fn new(conn: Arc<xcb::Connection>) -> Self {
+    pub fn new(conn: Arc<xcb::Connection>, screen: usize) -> Self {
         Self {
             conn: conn.clone(),
+            screen,
             atoms: AtomManager::new(conn),
         }
     }
 
-    /// Get the default root window.
+    /// Get the root window of the managed screen.
     fn get_root(&self) -> NerdResult<xcb::Window> {
-        match self.conn.get_setup().roots().next() {
+        match self.conn.get_setup().roots().nth(self.screen) {
             Some(root) => Ok(root.root()),
             None => Err(Error::Static("root window not found")),
         }
@@ -53,20 +104,49 @@ impl EWMHManager {
     /// Get supported protocols.
     pub fn get_net_supported(&self) -> NerdResult<Vec<xcb::Atom>> {
         Ok(vec![
+            self.atoms.get(protocols::_NET_SUPPORTING_WM_CHECK)?,
             self.atoms.get(protocols::_NET_WM_NAME)?,
             self.atoms.get(protocols::_NET_WM_PID)?,
             self.atoms.get(protocols::_NET_CLIENT_LIST)?,
+            self.atoms.get(protocols::_NET_CLIENT_LIST_STACKING)?,
             self.atoms.get(protocols::_NET_DESKTOP_NAMES)?,
             self.atoms.get(protocols::_NET_NUMBER_OF_DESKTOPS)?,
+            self.atoms.get(protocols::_NET_CURRENT_DESKTOP)?,
             self.atoms.get(protocols::_NET_ACTIVE_WINDOW)?,
+            self.atoms.get(protocols::_NET_WM_WINDOW_TYPE)?,
+            self.atoms.get(protocols::_NET_WM_WINDOW_TYPE_DOCK)?,
+            self.atoms.get(protocols::_NET_WM_WINDOW_TYPE_TOOLBAR)?,
+            self.atoms.get(protocols::_NET_WM_WINDOW_TYPE_UTILITY)?,
+            self.atoms.get(protocols::_NET_WM_WINDOW_TYPE_SPLASH)?,
+            self.atoms.get(protocols::_NET_WM_WINDOW_TYPE_DIALOG)?,
+            self.atoms.get(protocols::_NET_WM_STRUT)?,
+            self.atoms.get(protocols::_NET_WM_STRUT_PARTIAL)?,
+            self.atoms.get(protocols::_NET_WORKAREA)?,
+            self.atoms.get(protocols::_NET_WM_STATE)?,
+            self.atoms.get(protocols::_NET_WM_STATE_MAXIMIZED_VERT)?,
+            self.atoms.get(protocols::_NET_WM_STATE_MAXIMIZED_HORZ)?,
+            self.atoms.get(protocols::_NET_WM_STATE_ABOVE)?,
+            self.atoms.get(protocols::_NET_WM_STATE_BELOW)?,
+            self.atoms.get(protocols::_NET_WM_STATE_DEMANDS_ATTENTION)?,
+            self.atoms.get(protocols::_NET_FRAME_EXTENTS)?,
+            self.atoms.get(protocols::_NET_WM_USER_TIME)?,
+            self.atoms.get(protocols::_NET_WM_ALLOWED_ACTIONS)?,
+            self.atoms.get(protocols::_NET_WM_ACTION_MOVE)?,
+            self.atoms.get(protocols::_NET_WM_ACTION_RESIZE)?,
+            self.atoms.get(protocols::_NET_WM_ACTION_MAXIMIZE_HORZ)?,
+            self.atoms.get(protocols::_NET_WM_ACTION_MAXIMIZE_VERT)?,
+            self.atoms.get(protocols::_NET_WM_ACTION_CLOSE)?,
+            self.atoms.get(protocols::_NET_WM_ACTION_ABOVE)?,
+            self.atoms.get(protocols::_NET_WM_ACTION_BELOW)?,
         ])
     }
 
-    /// Change a property with type [`xcb::ATOM_ATOM`].
-    pub fn set_property_atom(
+    /// Change a property made of 32-bit values of type `ty`.
+    fn set_property_32(
         &self,
         window: xcb::Window,
         property: xcb::Atom,
+        ty: xcb::Atom,
         values: &[u32],
     ) -> NerdResult<()> {
         xcb::change_property(
@@ -74,7 +154,7 @@ impl EWMHManager {
             xcb::PROP_MODE_REPLACE as u8,
             window,
             property,
-            xcb::ATOM_ATOM,
+            ty,
             32,
             values,
         )
@@ -82,6 +162,50 @@ impl EWMHManager {
         Ok(())
     }
 
+    /// Read up to `len` 32-bit values of a property of type `ty`.
+    ///
+    /// Returns an empty list if the property is not set, or doesn't hold
+    /// 32-bit values.
+    pub fn get_property_32(
+        &self,
+        window: xcb::Window,
+        property: xcb::Atom,
+        ty: xcb::Atom,
+        len: u32,
+    ) -> NerdResult<Vec<u32>> {
+        let reply =
+            xcb::get_property(&self.conn, false, window, property, ty, 0, len).get_reply()?;
+        Ok(decode_property_32(reply.format(), reply.value()))
+    }
+
+    /// Read a property made of bytes, such as a string, of type `ty`.
+    ///
+    /// Returns [`None`] if the property is not set, or doesn't hold bytes.
+    pub fn get_property_8(
+        &self,
+        window: xcb::Window,
+        property: xcb::Atom,
+        ty: xcb::Atom,
+    ) -> NerdResult<Option<Vec<u8>>> {
+        let reply =
+            xcb::get_property(&self.conn, false, window, property, ty, 0, u32::MAX).get_reply()?;
+
+        if reply.format() != 8 {
+            return Ok(None);
+        }
+        Ok(Some(reply.value::<u8>().to_vec()))
+    }
+
+    /// Change a property with type [`xcb::ATOM_ATOM`].
+    pub fn set_property_atom(
+        &self,
+        window: xcb::Window,
+        property: xcb::Atom,
+        values: &[u32],
+    ) -> NerdResult<()> {
+        self.set_property_32(window, property, xcb::ATOM_ATOM, values)
+    }
+
     /// Change a property with type [`xcb::ATOM_CARDINAL`].
     pub fn set_property_cardinal(
         &self,
@@ -89,17 +213,7 @@ impl EWMHManager {
         property: xcb::Atom,
         values: &[u32],
     ) -> NerdResult<()> {
-        xcb::change_property(
-            &self.conn,
-            xcb::PROP_MODE_REPLACE as u8,
-            window,
-            property,
-            xcb::ATOM_CARDINAL,
-            32,
-            values,
-        )
-        .request_check()?;
-        Ok(())
+        self.set_property_32(window, property, xcb::ATOM_CARDINAL, values)
     }
 
     /// Change a property with type [`xcb::ATOM_STRING`].
@@ -136,17 +250,211 @@ impl EWMHManager {
         property: xcb::Atom,
         values: &[u32],
     ) -> NerdResult<()> {
-        xcb::change_property(
-            &self.conn,
-            xcb::PROP_MODE_REPLACE as u8,
+        self.set_property_32(window, property, xcb::ATOM_WINDOW, values)
+    }
+
+    /// Get the instance and class names of a window from its `WM_CLASS` property.
+    ///
+    /// The property consists of two consecutive null-terminated strings, the
+    /// instance name followed by the class name. Returns [`None`] if the property
+    /// is not set or is malformed.
+    pub fn get_class(&self, window: xcb::Window) -> NerdResult<Option<(String, String)>> {
+        Ok(self
+            .get_property_8(window, xcb::ATOM_WM_CLASS, xcb::ATOM_STRING)?
+            .and_then(|value| parse_class(&value)))
+    }
+
+    /// Get the title of a window.
+    ///
+    /// This reads `_NET_WM_NAME`, falling back to `WM_NAME` for clients that
+    /// don't set it. Returns [`None`] if neither property is set.
+    pub fn get_name(&self, window: xcb::Window) -> NerdResult<Option<String>> {
+        for (property, ty) in [
+            (
+                self.get_atom(protocols::_NET_WM_NAME)?,
+                self.get_atom("UTF8_STRING")?,
+            ),
+            (xcb::ATOM_WM_NAME, xcb::ATOM_ANY),
+        ] {
+            match self.get_property_8(window, property, ty)? {
+                Some(value) if !value.is_empty() => {
+                    return Ok(Some(String::from_utf8_lossy(&value).into_owned()));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Get the `_NET_WM_WINDOW_TYPE` of a window.
+    ///
+    /// The types are listed in order of preference. Returns an empty list if the
+    /// property is not set.
+    pub fn get_window_type(&self, window: xcb::Window) -> NerdResult<Vec<xcb::Atom>> {
+        self.get_property_32(
             window,
-            property,
-            xcb::ATOM_WINDOW,
-            32,
-            values,
+            self.get_atom(protocols::_NET_WM_WINDOW_TYPE)?,
+            xcb::ATOM_ATOM,
+            u32::MAX,
+        )
+    }
+
+    /// Get the states of a window from its `_NET_WM_STATE`.
+    ///
+    /// Returns an empty list if the property is not set.
+    pub fn get_state(&self, window: xcb::Window) -> NerdResult<Vec<xcb::Atom>> {
+        self.get_property_32(
+            window,
+            self.get_atom(protocols::_NET_WM_STATE)?,
+            xcb::ATOM_ATOM,
+            u32::MAX,
+        )
+    }
+
+    /// Check if a window's `_NET_WM_STATE` contains the state named `name`.
+    pub fn has_state(&self, window: xcb::Window, name: &'static str) -> NerdResult<bool> {
+        let atom = self.get_atom(name)?;
+        Ok(self.get_state(window)?.contains(&atom))
+    }
+
+    /// Set the `_NET_WM_WINDOW_OPACITY` compositors draw a window with,
+    /// from `0.0` for transparent to `1.0` for opaque.
+    ///
+    /// The property is removed from opaque windows, so compositors use their default.
+    pub fn set_opacity(&self, window: xcb::Window, opacity: f32) -> NerdResult<()> {
+        let property = self.get_atom(protocols::_NET_WM_WINDOW_OPACITY)?;
+        if opacity >= 1.0 {
+            xcb::delete_property_checked(&self.conn, window, property).request_check()?;
+            return Ok(());
+        }
+
+        let value = (opacity.max(0.0) as f64 * u32::MAX as f64) as u32;
+        self.set_property_cardinal(window, property, &[value])
+    }
+
+    /// Get the protocols a window takes part in from its `WM_PROTOCOLS`.
+    ///
+    /// Returns an empty list if the property is not set.
+    pub fn get_protocols(&self, window: xcb::Window) -> NerdResult<Vec<xcb::Atom>> {
+        self.get_property_32(
+            window,
+            self.get_atom(icccm::WM_PROTOCOLS)?,
+            xcb::ATOM_ATOM,
+            u32::MAX,
+        )
+    }
+
+    /// Check if a window's `WM_PROTOCOLS` contains the protocol named `name`.
+    pub fn has_protocol(&self, window: xcb::Window, name: &'static str) -> NerdResult<bool> {
+        let atom = self.get_atom(name)?;
+        Ok(self.get_protocols(window)?.contains(&atom))
+    }
+
+    /// Add or remove the states named `names` in a window's `_NET_WM_STATE`.
+    ///
+    /// Any other states are left as they are.
+    pub fn set_states(
+        &self,
+        window: xcb::Window,
+        names: &[&'static str],
+        enabled: bool,
+    ) -> NerdResult<()> {
+        let atoms = names
+            .iter()
+            .map(|n| self.get_atom(n))
+            .collect::<NerdResult<Vec<_>>>()?;
+
+        let mut state = self.get_state(window)?;
+        state.retain(|s| !atoms.contains(s));
+        if enabled {
+            state.extend(atoms);
+        }
+
+        self.set_property_atom(window, self.get_atom(protocols::_NET_WM_STATE)?, &state)
+    }
+
+    /// Add or remove the maximized states in a window's `_NET_WM_STATE`.
+    pub fn set_maximized(&self, window: xcb::Window, maximized: bool) -> NerdResult<()> {
+        self.set_states(
+            window,
+            &[
+                protocols::_NET_WM_STATE_MAXIMIZED_VERT,
+                protocols::_NET_WM_STATE_MAXIMIZED_HORZ,
+            ],
+            maximized,
         )
-        .request_check()?;
-        Ok(())
+    }
+
+    /// Check if the urgency flag is set in a window's `WM_HINTS`.
+    pub fn is_urgent(&self, window: xcb::Window) -> NerdResult<bool> {
+        /// Bit of the flags field set when the window needs attention.
+        const URGENCY_HINT: u32 = 1 << 8;
+
+        Ok(self
+            .get_property_32(window, xcb::ATOM_WM_HINTS, xcb::ATOM_WM_HINTS, 1)?
+            .first()
+            .is_some_and(|flags| flags & URGENCY_HINT != 0))
+    }
+
+    /// Check if a window is a dock or panel, based on its `_NET_WM_WINDOW_TYPE`.
+    pub fn is_dock(&self, window: xcb::Window) -> NerdResult<bool> {
+        let dock = self.get_atom(protocols::_NET_WM_WINDOW_TYPE_DOCK)?;
+        Ok(self.get_window_type(window)?.contains(&dock))
+    }
+
+    /// Check if a window should float instead of being tiled, based on
+    /// its `_NET_WM_WINDOW_TYPE`.
+    ///
+    /// This is true for dialogs, utility windows, toolbars and splash screens.
+    pub fn is_floating_type(&self, window: xcb::Window) -> NerdResult<bool> {
+        let floating = [
+            self.get_atom(protocols::_NET_WM_WINDOW_TYPE_DIALOG)?,
+            self.get_atom(protocols::_NET_WM_WINDOW_TYPE_UTILITY)?,
+            self.get_atom(protocols::_NET_WM_WINDOW_TYPE_TOOLBAR)?,
+            self.get_atom(protocols::_NET_WM_WINDOW_TYPE_SPLASH)?,
+        ];
+        Ok(self
+            .get_window_type(window)?
+            .iter()
+            .any(|t| floating.contains(t)))
+    }
+
+    /// Get the space reserved by a window at the edges of the screen.
+    ///
+    /// `_NET_WM_STRUT_PARTIAL` is preferred, falling back to `_NET_WM_STRUT`.
+    /// Returns [`None`] if the window sets neither.
+    pub fn get_strut(&self, window: xcb::Window) -> NerdResult<Option<Strut>> {
+        for name in [protocols::_NET_WM_STRUT_PARTIAL, protocols::_NET_WM_STRUT] {
+            let values =
+                self.get_property_32(window, self.get_atom(name)?, xcb::ATOM_CARDINAL, 12)?;
+            if let Some(strut) = Strut::from_values(&values) {
+                return Ok(Some(strut));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Get the window a dialog belongs to, from its `WM_TRANSIENT_FOR`.
+    pub fn get_transient_for(&self, window: xcb::Window) -> NerdResult<Option<xcb::Window>> {
+        Ok(self
+            .get_property_32(window, xcb::ATOM_WM_TRANSIENT_FOR, xcb::ATOM_WINDOW, 1)?
+            .first()
+            .copied()
+            .filter(|&w| w != xcb::NONE && w != window))
+    }
+
+    /// Check if a window asked not to be focused when it is first mapped, by
+    /// setting its `_NET_WM_USER_TIME` to `0`.
+    pub fn refuses_initial_focus(&self, window: xcb::Window) -> NerdResult<bool> {
+        let time = self.get_property_32(
+            window,
+            self.get_atom(protocols::_NET_WM_USER_TIME)?,
+            xcb::ATOM_CARDINAL,
+            1,
+        )?;
+        Ok(time.first() == Some(&0))
     }
 
     /// Hint supported protocols.
@@ -160,14 +468,34 @@ impl EWMHManager {
         Ok(())
     }
 
-    /// Set the `NET_WM_NAME` hint.
-    pub fn set_name(&self, name: &str) -> NerdResult<()> {
-        self.set_property_string(
-            self.get_root()?,
-            self.get_atom(protocols::_NET_WM_NAME)?,
-            &[name],
-        )?;
-        trace!("Successfully set name hint");
+    /// Create the `_NET_SUPPORTING_WM_CHECK` window, with `name` as its `_NET_WM_NAME`.
+    ///
+    /// The window is an unmapped child of the root, and both the root and the
+    /// window itself point to it, so clients can tell the window manager is running.
+    pub fn set_supporting_wm_check(&self, name: &str) -> NerdResult<()> {
+        let root = self.get_root()?;
+        let window = self.conn.generate_id();
+        xcb::create_window_checked(
+            &self.conn,
+            xcb::COPY_FROM_PARENT as u8,
+            window,
+            root,
+            -1,
+            -1,
+            1,
+            1,
+            0,
+            xcb::WINDOW_CLASS_INPUT_ONLY as u16,
+            xcb::COPY_FROM_PARENT,
+            &[(xcb::CW_OVERRIDE_REDIRECT, 1)],
+        )
+        .request_check()?;
+
+        let check = self.get_atom(protocols::_NET_SUPPORTING_WM_CHECK)?;
+        self.set_property_window(root, check, &[window])?;
+        self.set_property_window(window, check, &[window])?;
+        self.set_property_string(window, self.get_atom(protocols::_NET_WM_NAME)?, &[name])?;
+        trace!("Successfully set supporting wm check hint");
         Ok(())
     }
 
@@ -206,6 +534,87 @@ impl EWMHManager {
         Ok(())
     }
 
+    /// Change the `_NET_CURRENT_DESKTOP` hint.
+    pub fn update_current_desktop(&self, index: usize) -> NerdResult<()> {
+        self.set_property_cardinal(
+            self.get_root()?,
+            self.get_atom(protocols::_NET_CURRENT_DESKTOP)?,
+            &[index as u32],
+        )?;
+
+        trace!("Successfully set current desktop");
+        Ok(())
+    }
+
+    /// Update `_NET_WORKAREA` with the area available to clients on each
+    /// desktop, in the order desktops are numbered.
+    pub fn update_workarea(&self, areas: &[Rectangle]) -> NerdResult<()> {
+        let values: Vec<u32> = areas
+            .iter()
+            .flat_map(|a| [a.x as u32, a.y as u32, a.width as u32, a.height as u32])
+            .collect();
+
+        self.set_property_cardinal(
+            self.get_root()?,
+            self.get_atom(protocols::_NET_WORKAREA)?,
+            &values[..],
+        )?;
+
+        trace!("Successfully set work area");
+        Ok(())
+    }
+
+    /// Set the `_NET_FRAME_EXTENTS` of a window, the size of the decorations
+    /// added around it on each side.
+    pub fn set_frame_extents(
+        &self,
+        window: xcb::Window,
+        left: u32,
+        right: u32,
+        top: u32,
+        bottom: u32,
+    ) -> NerdResult<()> {
+        self.set_property_cardinal(
+            window,
+            self.get_atom(protocols::_NET_FRAME_EXTENTS)?,
+            &[left, right, top, bottom],
+        )
+    }
+
+    /// Set the `_NET_WM_ALLOWED_ACTIONS` of a window, so pagers and taskbars
+    /// only offer what the window manager lets users do with it.
+    ///
+    /// Every window can be closed, maximized and stacked above or below others,
+    /// but only floating windows can be moved and resized, as the layout owns
+    /// the geometry of tiled windows.
+    ///
+    /// The request is not checked, since it is sent whenever a client changes
+    /// between floating and tiled, and a window destroyed in the meantime is harmless.
+    pub fn set_allowed_actions(&self, window: xcb::Window, floating: bool) -> NerdResult<()> {
+        let mut actions = vec![
+            self.get_atom(protocols::_NET_WM_ACTION_CLOSE)?,
+            self.get_atom(protocols::_NET_WM_ACTION_MAXIMIZE_HORZ)?,
+            self.get_atom(protocols::_NET_WM_ACTION_MAXIMIZE_VERT)?,
+            self.get_atom(protocols::_NET_WM_ACTION_ABOVE)?,
+            self.get_atom(protocols::_NET_WM_ACTION_BELOW)?,
+        ];
+        if floating {
+            actions.push(self.get_atom(protocols::_NET_WM_ACTION_MOVE)?);
+            actions.push(self.get_atom(protocols::_NET_WM_ACTION_RESIZE)?);
+        }
+        xcb::change_property(
+            &self.conn,
+            xcb::PROP_MODE_REPLACE as u8,
+            window,
+            self.get_atom(protocols::_NET_WM_ALLOWED_ACTIONS)?,
+            xcb::ATOM_ATOM,
+            32,
+            &actions,
+        );
+        self.conn.flush();
+        Ok(())
+    }
+
     /// Change the `_NET_ACTIVE_WINDOW` hint.
     pub fn update_active_window(&self, active: Option<xcb::Window>) -> NerdResult<()> {
         let win = if let Some(w) = active { w } else { xcb::NONE };
@@ -220,7 +629,8 @@ impl EWMHManager {
         Ok(())
     }
 
-    /// Update `_NET_CLIENT_LIST` with the list of clients being managed.
+    /// Update `_NET_CLIENT_LIST` with the clients being managed, in the order
+    /// they were managed.
     pub fn update_client_list(&self, clients: &[xcb::Window]) -> NerdResult<()> {
         self.set_property_window(
             self.get_root()?,
@@ -231,4 +641,95 @@ impl EWMHManager {
         trace!("Successfully updated client list");
         Ok(())
     }
+
+    /// Update `_NET_CLIENT_LIST_STACKING` with the clients being managed,
+    /// from bottom to top.
+    pub fn update_client_list_stacking(&self, clients: &[xcb::Window]) -> NerdResult<()> {
+        self.set_property_window(
+            self.get_root()?,
+            self.get_atom(protocols::_NET_CLIENT_LIST_STACKING)?,
+            clients,
+        )?;
+
+        trace!("Successfully updated client stacking list");
+        Ok(())
+    }
+}
+
+/// Split a raw `WM_CLASS` value into its instance and class names.
+///
+/// Either name may be empty. The class ends at the next null byte, if any,
+/// so clients that leave out its terminator, or pad the value with extra
+/// null bytes, are handled too. Returns [`None`] if there is no class.
+fn parse_class(value: &[u8]) -> Option<(String, String)> {
+    let nul = value.iter().position(|b| *b == 0)?;
+    let (instance, rest) = (&value[..nul], &value[nul + 1..]);
+    let class = rest.split(|b| *b == 0).next().unwrap_or_default();
+
+    Some((
+        String::from_utf8_lossy(instance).into_owned(),
+        String::from_utf8_lossy(class).into_owned(),
+    ))
+}
+
+/// Decode the raw value of a property holding 32-bit values, such as atoms or
+/// windows, in the byte order of the server reply.
+///
+/// Properties that are not set are reported with a format of 0, and properties
+/// holding something else with a different format, so both give an empty list.
+fn decode_property_32(format: u8, value: &[u8]) -> Vec<u32> {
+    if format != 32 {
+        return vec![];
+    }
+    value
+        .chunks_exact(4)
+        .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_set_protocols() {
+        let protocols: [xcb::Atom; 2] = [301, 302];
+        let value: Vec<u8> = protocols.iter().flat_map(|a| a.to_ne_bytes()).collect();
+        assert_eq!(decode_property_32(32, &value), protocols);
+    }
+
+    #[test]
+    fn decode_unset_protocols() {
+        assert!(decode_property_32(0, &[]).is_empty());
+        assert!(decode_property_32(32, &[]).is_empty());
+    }
+
+    fn class(instance: &str, class: &str) -> Option<(String, String)> {
+        Some((instance.to_owned(), class.to_owned()))
+    }
+
+    #[test]
+    fn parse_full_class() {
+        assert_eq!(parse_class(b"xterm\0XTerm\0"), class("xterm", "XTerm"));
+        assert_eq!(parse_class(b"xterm\0XTerm"), class("xterm", "XTerm"));
+        assert_eq!(parse_class(b"xterm\0XTerm\0\0\0"), class("xterm", "XTerm"));
+    }
+
+    #[test]
+    fn parse_empty_names() {
+        assert_eq!(parse_class(b"\0Class\0"), class("", "Class"));
+        assert_eq!(parse_class(b"instance\0\0"), class("instance", ""));
+        assert_eq!(parse_class(b"\0"), class("", ""));
+    }
+
+    #[test]
+    fn parse_missing_class() {
+        assert_eq!(parse_class(b""), None);
+        assert_eq!(parse_class(b"instance"), None);
+    }
+
+    #[test]
+    fn decode_wrong_format() {
+        assert!(decode_property_32(8, b"WM_DELETE_WINDOW").is_empty());
+    }
 }
@@ -0,0 +1,260 @@
+//! Rectangles on the screen.
+
+use serde::{Deserialize, Serialize};
+
+/// Area of the screen, with its position relative to the root window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+pub struct Rectangle {
+    pub x: i16,
+    pub y: i16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rectangle {
+    #[must_use]
+    pub fn new(x: i16, y: i16, width: u16, height: u16) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Check if the point `(x, y)` is inside this rectangle.
+    pub fn contains(&self, x: i16, y: i16) -> bool {
+        let (x, y) = (x as i32, y as i32);
+        x >= self.x as i32
+            && y >= self.y as i32
+            && x < self.x as i32 + self.width as i32
+            && y < self.y as i32 + self.height as i32
+    }
+
+    /// Get the point in the middle of this rectangle.
+    pub fn center(&self) -> (i16, i16) {
+        (
+            self.x + (self.width / 2) as i16,
+            self.y + (self.height / 2) as i16,
+        )
+    }
+
+    /// Shrink this rectangle by the depths in `reserved`, as
+    /// `[left, right, top, bottom]`.
+    pub fn shrink(&self, reserved: [u32; 4]) -> Self {
+        let [left, right, top, bottom] = reserved.map(|r| r.min(u16::MAX as u32) as u16);
+        Self::new(
+            self.x.saturating_add(left.min(self.width) as i16),
+            self.y.saturating_add(top.min(self.height) as i16),
+            self.width.saturating_sub(left.saturating_add(right)),
+            self.height.saturating_sub(top.saturating_add(bottom)),
+        )
+    }
+
+    /// Shrink this rectangle by `n` on every side.
+    pub fn inset(&self, n: u16) -> Self {
+        let offset = n.min(i16::MAX as u16) as i16;
+        Self::new(
+            self.x.saturating_add(offset),
+            self.y.saturating_add(offset),
+            self.width.saturating_sub(n.saturating_mul(2)),
+            self.height.saturating_sub(n.saturating_mul(2)),
+        )
+    }
+
+    /// Get the area shared by this rectangle and `other`, if they overlap.
+    pub fn intersection(&self, other: Rectangle) -> Option<Self> {
+        let left = (self.x as i32).max(other.x as i32);
+        let top = (self.y as i32).max(other.y as i32);
+        let right = (self.x as i32 + self.width as i32).min(other.x as i32 + other.width as i32);
+        let bottom = (self.y as i32 + self.height as i32).min(other.y as i32 + other.height as i32);
+        if right <= left || bottom <= top {
+            return None;
+        }
+        Some(Self::new(
+            left as i16,
+            top as i16,
+            (right - left) as u16,
+            (bottom - top) as u16,
+        ))
+    }
+
+    /// Get a rectangle of the given size, centered on this one.
+    ///
+    /// If it doesn't fit, it is aligned to the top left corner instead.
+    pub fn center_size(&self, width: u16, height: u16) -> Self {
+        Self::new(
+            self.x + (self.width.saturating_sub(width) / 2) as i16,
+            self.y + (self.height.saturating_sub(height) / 2) as i16,
+            width,
+            height,
+        )
+    }
+
+    /// Split this rectangle into a left and a right part, `gap` apart.
+    ///
+    /// The left part gets `ratio` of the width left after the gap.
+    pub fn split_horizontal(&self, ratio: f32, gap: u16) -> (Self, Self) {
+        let first = (self.width.saturating_sub(gap) as f32 * ratio) as u16;
+        let offset = first + gap;
+        (
+            Self::new(self.x, self.y, first, self.height),
+            Self::new(
+                self.x + offset as i16,
+                self.y,
+                self.width.saturating_sub(offset),
+                self.height,
+            ),
+        )
+    }
+
+    /// Split this rectangle into a top and a bottom part, `gap` apart.
+    ///
+    /// The top part gets `ratio` of the height left after the gap.
+    pub fn split_vertical(&self, ratio: f32, gap: u16) -> (Self, Self) {
+        let first = (self.height.saturating_sub(gap) as f32 * ratio) as u16;
+        let offset = first + gap;
+        (
+            Self::new(self.x, self.y, self.width, first),
+            Self::new(
+                self.x,
+                self.y + offset as i16,
+                self.width,
+                self.height.saturating_sub(offset),
+            ),
+        )
+    }
+}
+
+impl From<Rectangle> for xcb::Rectangle {
+    fn from(other: Rectangle) -> Self {
+        xcb::Rectangle::new(other.x, other.y, other.width, other.height)
+    }
+}
+
+/// Space a dock reserves along the edges of the screen, from its
+/// `_NET_WM_STRUT_PARTIAL`.
+///
+/// Each edge is reserved to a depth measured from that edge of the screen, but
+/// only along part of its length, so docks only take space from the monitors
+/// they are on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Strut {
+    /// Depth reserved on each edge, as `[left, right, top, bottom]`.
+    sizes: [u32; 4],
+    /// First and last pixel reserved along each edge, in the same order.
+    ranges: [(u32, u32); 4],
+}
+
+impl Strut {
+    /// Parse the values of a `_NET_WM_STRUT_PARTIAL`, or the first four of a
+    /// `_NET_WM_STRUT`, which reserve the whole length of each edge.
+    ///
+    /// Returns [`None`] if there are less than four values.
+    pub fn from_values(values: &[u32]) -> Option<Self> {
+        let (sizes, partial) = match values {
+            [left, right, top, bottom, partial @ ..] => ([*left, *right, *top, *bottom], partial),
+            _ => return None,
+        };
+        let mut ranges = [(0, u32::MAX); 4];
+        if partial.len() >= 8 {
+            for (range, pair) in ranges.iter_mut().zip(partial.chunks_exact(2)) {
+                *range = (pair[0], pair[1]);
+            }
+        }
+        Some(Self { sizes, ranges })
+    }
+
+    /// Get the depth this strut reserves on each edge of `monitor`, as
+    /// `[left, right, top, bottom]`, on a screen `width` by `height`.
+    ///
+    /// Edges only reserve space if their range overlaps the monitor, and reach
+    /// into it from the edge of the screen.
+    pub fn get_reserved(&self, monitor: Rectangle, width: u32, height: u32) -> [u32; 4] {
+        let (left, top) = (monitor.x as i64, monitor.y as i64);
+        let (right, bottom) = (left + monitor.width as i64, top + monitor.height as i64);
+        let overlaps = |(start, end): (u32, u32), from: i64, to: i64| {
+            (start as i64) < to && (end as i64) >= from
+        };
+
+        let [l, r, t, b] = self.sizes.map(|s| s as i64);
+        let [lr, rr, tr, br] = self.ranges;
+        let depths = [
+            (overlaps(lr, top, bottom), l - left, monitor.width),
+            (
+                overlaps(rr, top, bottom),
+                right - (width as i64 - r),
+                monitor.width,
+            ),
+            (overlaps(tr, left, right), t - top, monitor.height),
+            (
+                overlaps(br, left, right),
+                bottom - (height as i64 - b),
+                monitor.height,
+            ),
+        ];
+        depths.map(|(overlaps, depth, max)| {
+            if overlaps {
+                depth.clamp(0, max as i64) as u32
+            } else {
+                0
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 1920x1080 monitor next to a taller 1000x1200 one.
+    const WIDTH: u32 = 2920;
+    const HEIGHT: u32 = 1200;
+    const LEFT: Rectangle = Rectangle {
+        x: 0,
+        y: 0,
+        width: 1920,
+        height: 1080,
+    };
+    const RIGHT: Rectangle = Rectangle {
+        x: 1920,
+        y: 0,
+        width: 1000,
+        height: 1200,
+    };
+
+    #[test]
+    fn partial_strut_on_one_monitor() {
+        // A 30 pixel panel along the bottom of the shorter monitor, measured
+        // from the bottom of the screen.
+        let strut = Strut::from_values(&[0, 0, 0, 150, 0, 0, 0, 0, 0, 0, 0, 1919]).unwrap();
+        assert_eq!(strut.get_reserved(LEFT, WIDTH, HEIGHT), [0, 0, 0, 30]);
+        assert_eq!(strut.get_reserved(RIGHT, WIDTH, HEIGHT), [0, 0, 0, 0]);
+        assert_eq!(
+            LEFT.shrink(strut.get_reserved(LEFT, WIDTH, HEIGHT)),
+            Rectangle::new(0, 0, 1920, 1050)
+        );
+    }
+
+    #[test]
+    fn full_strut_on_every_monitor() {
+        let strut = Strut::from_values(&[0, 0, 20, 0]).unwrap();
+        assert_eq!(strut.get_reserved(LEFT, WIDTH, HEIGHT), [0, 0, 20, 0]);
+        assert_eq!(strut.get_reserved(RIGHT, WIDTH, HEIGHT), [0, 0, 20, 0]);
+    }
+
+    #[test]
+    fn inset_by_large_gap() {
+        assert_eq!(LEFT.inset(10), Rectangle::new(10, 10, 1900, 1060));
+        assert_eq!(
+            RIGHT.inset(u16::MAX),
+            Rectangle::new(i16::MAX, i16::MAX, 0, 0)
+        );
+    }
+
+    #[test]
+    fn too_few_values() {
+        assert_eq!(Strut::from_values(&[]), None);
+        assert_eq!(Strut::from_values(&[0, 0, 20]), None);
+    }
+}
@@ -0,0 +1,220 @@
+//! Control the window manager over a Unix domain socket.
+//!
+//! The socket is created at `$XDG_RUNTIME_DIR/nerdwm.sock`. Clients write
+//! newline-delimited commands to it, and receive one line in response for
+//! each command: `ok` if the command was accepted, or `error: <reason>`
+//! otherwise.
+//!
+//! Supported commands:
+//! - `switch-desktop <index>`
+//!   Switch to the desktop at `<index>`, counting from 0.
+//! - `close-window`
+//!   Close the focused window.
+//! - `focus-next`, `focus-prev`
+//!   Focus the next or previous window on the active desktop.
+//! - `focus-mru`
+//!   Focus the window that was focused before the current one.
+//! - `swap-master`
+//!   Move the focused window to the master position.
+//! - `move-stack-up`, `move-stack-down`
+//!   Swap the focused window with its neighbour in the stack.
+//! - `inc-master-ratio`, `dec-master-ratio`
+//!   Grow or shrink the master area of the current layout.
+//! - `toggle-scratchpad`
+//!   Show or hide the scratchpad window.
+//! - `toggle-above`
+//!   Keep the focused window above all other windows, or stop doing so.
+//! - `toggle-floating`
+//!   Take the focused window out of the layout, or put it back in.
+//! - `rotate-split`, `flip-split`
+//!   Rotate or flip the split holding the focused window, in layouts with splits.
+//! - `toggle-maximize`
+//!   Maximize the focused window, or restore it if it is already maximized.
+//! - `snap-left`, `snap-right`, `snap-top`, `snap-bottom`, `snap-maximize`
+//!   Snap the focused window to half of the desktop, or all of it, making it
+//!   floating. Snapping it to the same edge again switches between a half and
+//!   a third.
+//! - `unsnap`
+//!   Restore the focused window to where it was before it was snapped.
+//! - `cycle-layout`
+//!   Switch to the next layout on the active desktop.
+//! - `focus-monitor-next`, `focus-monitor-prev`
+//!   Focus the monitor to the right or left of the focused one, wrapping around.
+//! - `move-to-monitor-next`, `move-to-monitor-prev`
+//!   Move the focused window to the desktop shown on the monitor to the right
+//!   or left.
+//! - `quit`
+//!   Quit the window manager.
+//! - `restart`
+//!   Restart the window manager in place, keeping windows on their desktops.
+//! - `dump-state`
+//!   Respond with the monitors, desktops and clients as a single line of
+//!   JSON, instead of `ok`.
+//!
+//! # Examples
+//! ```sh
+//! echo "switch-desktop 1" | socat - UNIX-CONNECT:$XDG_RUNTIME_DIR/nerdwm.sock
+//! ```
+
+use std::path::PathBuf;
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, oneshot};
+
+use super::actions::ActionType;
+use crate::prelude::*;
+
+/// Command received on the control socket.
+pub enum Request {
+    /// Perform an action.
+    Action(ActionType),
+    /// Reply with a snapshot of the window manager's state as JSON, or the
+    /// reason it couldn't be taken.
+    DumpState(oneshot::Sender<Result<String, String>>),
+}
+
+/// Listens for commands on the control socket.
+///
+/// Commands are parsed into [`Request`]s, which can be received with [`IpcServer::recv`].
+pub struct IpcServer {
+    path: PathBuf,
+    requests: mpsc::UnboundedReceiver<Request>,
+}
+
+impl IpcServer {
+    /// Create the control socket and start accepting connections on it.
+    ///
+    /// Any stale socket left behind by a previous instance is removed first.
+    pub fn bind() -> NerdResult<Self> {
+        let path = xdg::BaseDirectories::new()
+            .map_err(|e| Error::Other(e.to_string()))?
+            .get_runtime_directory()
+            .map_err(|e| Error::Other(e.to_string()))?
+            .join("nerdwm.sock");
+
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+
+        let listener = UnixListener::bind(&path)?;
+        let (tx, requests) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(handle_client(stream, tx.clone()));
+                    }
+                    Err(e) => {
+                        error!("Failed to accept IPC connection: {}", e);
+                    }
+                }
+            }
+        });
+
+        info!("Listening for commands on {:?}", path);
+        Ok(Self { path, requests })
+    }
+
+    /// Wait for the next command.
+    pub async fn recv(&mut self) -> Option<Request> {
+        self.requests.recv().await
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Read commands from a single client until it disconnects.
+async fn handle_client(stream: UnixStream, tx: mpsc::UnboundedSender<Request>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim() == "dump-state" {
+            let (reply, response) = oneshot::channel();
+            if tx.send(Request::DumpState(reply)).is_err() {
+                break;
+            }
+            let response = match response.await {
+                Ok(Ok(json)) => format!("{}\n", json),
+                Ok(Err(e)) => format!("error: {}\n", e),
+                Err(_) => break,
+            };
+            if writer.write_all(response.as_bytes()).await.is_err() {
+                break;
+            }
+            continue;
+        }
+
+        let response = match parse_command(&line) {
+            Ok(action) => {
+                trace!("Received command {:?}", action);
+                if tx.send(Request::Action(action)).is_err() {
+                    break;
+                }
+                "ok\n".to_owned()
+            }
+            Err(e) => format!("error: {}\n", e),
+        };
+
+        if writer.write_all(response.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Parse a single command into the action it maps to.
+fn parse_command(line: &str) -> Result<ActionType, String> {
+    let mut args = line.split_whitespace();
+
+    let action = match args.next() {
+        Some("switch-desktop") => {
+            let index = args.next().ok_or("missing desktop index")?;
+            let index = index
+                .parse()
+                .map_err(|_| format!("invalid desktop index `{}`", index))?;
+            ActionType::SwitchDesktop(index)
+        }
+        Some("close-window") => ActionType::WindowClose,
+        Some("focus-next") => ActionType::FocusNext,
+        Some("focus-prev") => ActionType::FocusPrev,
+        Some("focus-mru") => ActionType::FocusMruNext,
+        Some("swap-master") => ActionType::SwapMaster,
+        Some("move-stack-up") => ActionType::MoveStackUp,
+        Some("move-stack-down") => ActionType::MoveStackDown,
+        Some("inc-master-ratio") => ActionType::IncMasterRatio,
+        Some("dec-master-ratio") => ActionType::DecMasterRatio,
+        Some("toggle-scratchpad") => ActionType::ToggleScratchpad,
+        Some("toggle-above") => ActionType::ToggleAbove,
+        Some("toggle-floating") => ActionType::ToggleFloating,
+        Some("rotate-split") => ActionType::RotateSplit,
+        Some("flip-split") => ActionType::FlipSplit,
+        Some("toggle-maximize") => ActionType::ToggleMaximize,
+        Some("snap-left") => ActionType::SnapLeft,
+        Some("snap-right") => ActionType::SnapRight,
+        Some("snap-top") => ActionType::SnapTop,
+        Some("snap-bottom") => ActionType::SnapBottom,
+        Some("snap-maximize") => ActionType::SnapMaximize,
+        Some("unsnap") => ActionType::Unsnap,
+        Some("cycle-layout") => ActionType::CycleLayout,
+        Some("focus-monitor-next") => ActionType::FocusMonitorNext,
+        Some("focus-monitor-prev") => ActionType::FocusMonitorPrev,
+        Some("move-to-monitor-next") => ActionType::MoveWindowToMonitorNext,
+        Some("move-to-monitor-prev") => ActionType::MoveWindowToMonitorPrev,
+        Some("quit") => ActionType::WindowManagerQuit,
+        Some("restart") => ActionType::WindowManagerRestart,
+        Some(other) => return Err(format!("unknown command `{}`", other)),
+        None => return Err("empty command".to_owned()),
+    };
+
+    if args.next().is_some() {
+        return Err("too many arguments".to_owned());
+    }
+
+    Ok(action)
+}
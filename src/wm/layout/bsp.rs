@@ -0,0 +1,455 @@
+//! Binary space partitioning layout.
+
+use std::cell::{Cell, RefCell};
+
+use super::Layout;
+use crate::prelude::*;
+use crate::wm::client::ClientWindow;
+use crate::wm::config::LayoutConfig;
+use crate::wm::geometry::Rectangle;
+
+/// Node in the tree of splits.
+#[derive(Debug)]
+enum Node {
+    Leaf(xcb::Window),
+    Split {
+        /// Whether the children are side by side, rather than one above the other.
+        vertical: bool,
+        /// Fraction of the area given to the first child.
+        ratio: f32,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+impl Node {
+    /// Check if `window` is a leaf under this node.
+    fn contains(&self, window: xcb::Window) -> bool {
+        match self {
+            Self::Leaf(w) => *w == window,
+            Self::Split { first, second, .. } => first.contains(window) || second.contains(window),
+        }
+    }
+
+    /// Get the windows under this node, in order.
+    fn get_windows(&self, windows: &mut Vec<xcb::Window>) {
+        match self {
+            Self::Leaf(w) => windows.push(*w),
+            Self::Split { first, second, .. } => {
+                first.get_windows(windows);
+                second.get_windows(windows);
+            }
+        }
+    }
+
+    /// Split the leaf holding `target`, which covers `area`, to make room for `window`.
+    ///
+    /// The leaf is split along its longer side. Returns `false` if there is no such leaf.
+    fn insert(
+        &mut self,
+        area: Rectangle,
+        gap: u16,
+        target: xcb::Window,
+        window: xcb::Window,
+    ) -> bool {
+        match self {
+            Self::Leaf(w) if *w == target => {
+                *self = Self::Split {
+                    vertical: area.width >= area.height,
+                    ratio: 0.5,
+                    first: Box::new(Self::Leaf(target)),
+                    second: Box::new(Self::Leaf(window)),
+                };
+                true
+            }
+            Self::Leaf(_) => false,
+            Self::Split {
+                vertical,
+                ratio,
+                first,
+                second,
+            } => {
+                let (first_area, second_area) = split(area, *vertical, *ratio, gap);
+                first.insert(first_area, gap, target, window)
+                    || second.insert(second_area, gap, target, window)
+            }
+        }
+    }
+
+    /// Remove the leaf holding `window` from under this node, collapsing its
+    /// sibling into their parent.
+    ///
+    /// Returns `None` if this node is that leaf.
+    fn remove(self, window: xcb::Window) -> Option<Self> {
+        match self {
+            Self::Leaf(w) if w == window => None,
+            Self::Split {
+                vertical,
+                ratio,
+                first,
+                second,
+            } => match (first.remove(window), second.remove(window)) {
+                (Some(first), Some(second)) => Some(Self::Split {
+                    vertical,
+                    ratio,
+                    first: Box::new(first),
+                    second: Box::new(second),
+                }),
+                (Some(node), None) | (None, Some(node)) => Some(node),
+                (None, None) => None,
+            },
+            leaf => Some(leaf),
+        }
+    }
+
+    /// Find the split directly above the leaf holding `window`.
+    ///
+    /// Also returns whether the leaf is the first child of the split.
+    fn get_parent_mut(&mut self, window: xcb::Window) -> Option<(&mut Self, bool)> {
+        let (in_first, in_second) = match self {
+            Self::Leaf(_) => return None,
+            Self::Split { first, second, .. } => (
+                matches!(**first, Self::Leaf(w) if w == window),
+                matches!(**second, Self::Leaf(w) if w == window),
+            ),
+        };
+
+        if in_first || in_second {
+            return Some((self, in_first));
+        }
+
+        match self {
+            Self::Leaf(_) => None,
+            Self::Split { first, second, .. } => {
+                if first.contains(window) {
+                    first.get_parent_mut(window)
+                } else {
+                    second.get_parent_mut(window)
+                }
+            }
+        }
+    }
+
+    /// Compute the geometry of every window under this node inside `area`.
+    fn get_geometries(
+        &self,
+        area: Rectangle,
+        gap: u16,
+        geometries: &mut Vec<(xcb::Window, Rectangle)>,
+    ) {
+        match self {
+            Self::Leaf(window) => geometries.push((*window, area)),
+            Self::Split {
+                vertical,
+                ratio,
+                first,
+                second,
+            } => {
+                let (first_area, second_area) = split(area, *vertical, *ratio, gap);
+                first.get_geometries(first_area, gap, geometries);
+                second.get_geometries(second_area, gap, geometries);
+            }
+        }
+    }
+}
+
+/// Split `area` in two, `gap` pixels apart, giving `ratio` of it to the first part.
+fn split(area: Rectangle, vertical: bool, ratio: f32, gap: u16) -> (Rectangle, Rectangle) {
+    if vertical {
+        area.split_horizontal(ratio, gap)
+    } else {
+        area.split_vertical(ratio, gap)
+    }
+}
+
+/// Keeps clients in a binary tree of splits.
+///
+/// Each new client splits the area of the focused client in two, along its
+/// longer side. When a client goes away, the other half of its split takes
+/// over the whole split. The split holding the focused client can be rotated,
+/// flipped, and resized with [`Layout::adjust_master`].
+#[derive(Default)]
+pub struct BspLayout {
+    root: RefCell<Option<Node>>,
+    /// Most recently focused client.
+    focused: Cell<Option<xcb::Window>>,
+    /// Client that was focused before [`BspLayout::focused`]. New clients are
+    /// focused before they are added to the tree, so they split this one instead.
+    previous: Cell<Option<xcb::Window>>,
+}
+
+impl BspLayout {
+    /// Smallest fraction of a split either side can occupy.
+    const MIN_RATIO: f32 = 0.05;
+    /// Largest fraction of a split either side can occupy.
+    const MAX_RATIO: f32 = 0.95;
+
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bring the tree in line with `clients`.
+    ///
+    /// Clients no longer in the list are removed, and new ones are inserted
+    /// next to the focused client, in stack order.
+    fn sync(&self, area: Rectangle, gap: u16, clients: &[xcb::Window]) {
+        let mut root = self.root.borrow_mut();
+
+        let mut windows = vec![];
+        if let Some(node) = root.as_ref() {
+            node.get_windows(&mut windows);
+        }
+        for window in windows {
+            if !clients.contains(&window) {
+                *root = root.take().and_then(|n| n.remove(window));
+            }
+        }
+
+        for &window in clients.iter().rev() {
+            let node = match root.as_mut() {
+                Some(node) => node,
+                None => {
+                    *root = Some(Node::Leaf(window));
+                    continue;
+                }
+            };
+            if node.contains(window) {
+                continue;
+            }
+
+            let mut windows = vec![];
+            node.get_windows(&mut windows);
+            let target = [self.focused.get(), self.previous.get()]
+                .into_iter()
+                .flatten()
+                .find(|w| windows.contains(w))
+                .or_else(|| windows.last().copied());
+            if let Some(target) = target {
+                node.insert(area, gap, target, window);
+            }
+        }
+    }
+
+    /// Update the tree with `windows` and compute the geometry of each of
+    /// them inside `area`.
+    ///
+    /// This doesn't touch the X server, so it can be used without a connection.
+    pub fn get_geometries(
+        &self,
+        area: Rectangle,
+        config: &LayoutConfig,
+        windows: &[xcb::Window],
+    ) -> Vec<(xcb::Window, Rectangle)> {
+        let area = area.inset(config.get_outer_gap());
+        let gap = config.get_gap_size();
+        self.sync(area, gap, windows);
+
+        let mut geometries = vec![];
+        if let Some(node) = self.root.borrow().as_ref() {
+            node.get_geometries(area, gap, &mut geometries);
+        }
+        geometries
+    }
+
+    /// Apply `f` to the split holding the focused client, along with whether
+    /// the client is its first child.
+    ///
+    /// Returns `false` if the focused client isn't in a split.
+    fn with_focused_split(&mut self, f: impl FnOnce(&mut Node, bool)) -> bool {
+        let focused = match self.focused.get() {
+            Some(f) => f,
+            None => return false,
+        };
+
+        match self
+            .root
+            .get_mut()
+            .as_mut()
+            .and_then(|n| n.get_parent_mut(focused))
+        {
+            Some((node, is_first)) => {
+                f(node, is_first);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Layout for BspLayout {
+    fn get_name(&self) -> &'static str {
+        "bsp"
+    }
+
+    fn configure(
+        &self,
+        _: &xcb::Connection,
+        area: Rectangle,
+        config: &LayoutConfig,
+        clients: &[ClientWindow],
+    ) -> NerdResult<Vec<(xcb::Window, Rectangle)>> {
+        let windows: Vec<_> = clients.iter().map(|c| c.get_window()).collect();
+        Ok(self.get_geometries(area, config, &windows))
+    }
+
+    /// Grow the focused client's side of its split by `delta`.
+    fn adjust_master(&mut self, delta: f32) -> bool {
+        self.with_focused_split(|node, is_first| {
+            if let Node::Split { ratio, .. } = node {
+                let delta = if is_first { delta } else { -delta };
+                *ratio = (*ratio + delta).clamp(Self::MIN_RATIO, Self::MAX_RATIO);
+            }
+        })
+    }
+
+    fn rotate(&mut self) -> bool {
+        self.with_focused_split(|node, _| {
+            if let Node::Split { vertical, .. } = node {
+                *vertical = !*vertical;
+            }
+        })
+    }
+
+    fn flip(&mut self) -> bool {
+        self.with_focused_split(|node, _| {
+            if let Node::Split { first, second, .. } = node {
+                std::mem::swap(first, second);
+            }
+        })
+    }
+
+    fn focus(&self, window: xcb::Window) {
+        if self.focused.get() != Some(window) {
+            self.previous.set(self.focused.get());
+            self.focused.set(Some(window));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: Rectangle = Rectangle {
+        x: 0,
+        y: 0,
+        width: 1000,
+        height: 600,
+    };
+
+    fn config() -> LayoutConfig {
+        toml::from_str("gap_size = 0").unwrap()
+    }
+
+    /// Create a layout with windows `1` on the left and `2` on the right,
+    /// with `2` focused.
+    fn two_windows() -> BspLayout {
+        let layout = BspLayout::new();
+        layout.focus(1);
+        layout.get_geometries(AREA, &config(), &[1]);
+        // New clients are focused before they are laid out.
+        layout.focus(2);
+        assert_eq!(
+            layout.get_geometries(AREA, &config(), &[2, 1]),
+            vec![
+                (1, Rectangle::new(0, 0, 500, 600)),
+                (2, Rectangle::new(500, 0, 500, 600)),
+            ]
+        );
+        layout
+    }
+
+    #[test]
+    fn new_window_splits_previous() {
+        let layout = two_windows();
+        layout.focus(3);
+        assert_eq!(
+            layout.get_geometries(AREA, &config(), &[3, 2, 1]),
+            vec![
+                (1, Rectangle::new(0, 0, 500, 600)),
+                (2, Rectangle::new(500, 0, 500, 300)),
+                (3, Rectangle::new(500, 300, 500, 300)),
+            ]
+        );
+    }
+
+    #[test]
+    fn new_window_splits_focused() {
+        let layout = two_windows();
+        layout.focus(1);
+        layout.focus(3);
+        assert_eq!(
+            layout.get_geometries(AREA, &config(), &[3, 1, 2]),
+            vec![
+                (1, Rectangle::new(0, 0, 500, 300)),
+                (3, Rectangle::new(0, 300, 500, 300)),
+                (2, Rectangle::new(500, 0, 500, 600)),
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_collapses_sibling() {
+        let layout = two_windows();
+        layout.focus(3);
+        layout.get_geometries(AREA, &config(), &[3, 2, 1]);
+        assert_eq!(
+            layout.get_geometries(AREA, &config(), &[2, 1]),
+            vec![
+                (1, Rectangle::new(0, 0, 500, 600)),
+                (2, Rectangle::new(500, 0, 500, 600)),
+            ]
+        );
+        assert_eq!(
+            layout.get_geometries(AREA, &config(), &[1]),
+            vec![(1, AREA)]
+        );
+    }
+
+    #[test]
+    fn flip_and_rotate() {
+        let mut layout = two_windows();
+        assert!(layout.flip());
+        assert_eq!(
+            layout.get_geometries(AREA, &config(), &[2, 1]),
+            vec![
+                (2, Rectangle::new(0, 0, 500, 600)),
+                (1, Rectangle::new(500, 0, 500, 600)),
+            ]
+        );
+        assert!(layout.rotate());
+        assert_eq!(
+            layout.get_geometries(AREA, &config(), &[2, 1]),
+            vec![
+                (2, Rectangle::new(0, 0, 1000, 300)),
+                (1, Rectangle::new(0, 300, 1000, 300)),
+            ]
+        );
+    }
+
+    #[test]
+    fn single_window_has_no_split() {
+        let mut layout = BspLayout::new();
+        layout.focus(1);
+        layout.get_geometries(AREA, &config(), &[1]);
+        assert!(!layout.flip());
+        assert!(!layout.rotate());
+        assert!(!layout.adjust_master(0.1));
+    }
+
+    #[test]
+    fn ratio_is_clamped() {
+        let mut layout = two_windows();
+        // The focused window is on the right, so growing it shrinks the left.
+        assert!(layout.adjust_master(10.0));
+        let geometries = layout.get_geometries(AREA, &config(), &[2, 1]);
+        let width = (AREA.width as f32 * BspLayout::MIN_RATIO) as u16;
+        assert_eq!(geometries[0], (1, Rectangle::new(0, 0, width, 600)));
+
+        assert!(layout.adjust_master(-10.0));
+        let geometries = layout.get_geometries(AREA, &config(), &[2, 1]);
+        let width = (AREA.width as f32 * BspLayout::MAX_RATIO) as u16;
+        assert_eq!(geometries[0], (1, Rectangle::new(0, 0, width, 600)));
+    }
+}
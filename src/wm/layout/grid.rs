@@ -0,0 +1,120 @@
+//! Grid tiling layout.
+
+use super::Layout;
+use crate::prelude::*;
+use crate::wm::client::ClientWindow;
+use crate::wm::config::LayoutConfig;
+use crate::wm::geometry::Rectangle;
+
+/// Split a span `length` long starting at `start` into `parts` of equal
+/// length, `gap` apart, and get the start and length of the part at `index`.
+///
+/// The last part takes up any remaining space.
+fn split(start: i16, length: u16, parts: u32, gap: u32, index: u32) -> (i16, u16) {
+    let length = u32::from(length);
+    let size = length.saturating_sub(gap * (parts - 1)) / parts;
+    let offset = ((size + gap) * index).min(length);
+    let size = if index == parts - 1 {
+        length - offset
+    } else {
+        size
+    };
+    (start.saturating_add(offset as i16), size as u16)
+}
+
+/// Tiles clients in rows of equal size, with as many columns as rows, or
+/// one more. Clients on the last row share its whole width.
+pub struct GridLayout {}
+
+impl GridLayout {
+    /// Compute the geometry of `count` clients inside `area`, in stack order.
+    ///
+    /// This doesn't touch the X server, so it can be used without a connection.
+    pub fn get_geometries(
+        &self,
+        area: Rectangle,
+        config: &LayoutConfig,
+        count: usize,
+    ) -> Vec<Rectangle> {
+        let area = area.inset(config.get_outer_gap());
+        let gap = u32::from(config.get_gap_size());
+        if count == 0 {
+            return vec![];
+        }
+
+        let count = count as u32;
+        let columns = (count as f32).sqrt().ceil() as u32;
+        let rows = count.div_ceil(columns);
+
+        (0..count)
+            .map(|i| {
+                let (row, column) = (i / columns, i % columns);
+                let row_columns = if row == rows - 1 {
+                    count - columns * (rows - 1)
+                } else {
+                    columns
+                };
+                let (x, width) = split(area.x, area.width, row_columns, gap, column);
+                let (y, height) = split(area.y, area.height, rows, gap, row);
+                Rectangle::new(x, y, width, height)
+            })
+            .collect()
+    }
+}
+
+impl Layout for GridLayout {
+    fn get_name(&self) -> &'static str {
+        "grid"
+    }
+
+    fn configure(
+        &self,
+        _: &xcb::Connection,
+        area: Rectangle,
+        config: &LayoutConfig,
+        clients: &[ClientWindow],
+    ) -> NerdResult<Vec<(xcb::Window, Rectangle)>> {
+        let geometries = self.get_geometries(area, config, clients.len());
+        Ok(clients
+            .iter()
+            .map(|c| c.get_window())
+            .zip(geometries)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(gap_size: u16) -> LayoutConfig {
+        toml::from_str(&format!("gap_size = {}", gap_size)).unwrap()
+    }
+
+    #[test]
+    fn four_windows() {
+        let area = Rectangle::new(0, 0, 1010, 610);
+        assert_eq!(
+            GridLayout {}.get_geometries(area, &config(10), 4),
+            vec![
+                Rectangle::new(0, 0, 500, 300),
+                Rectangle::new(510, 0, 500, 300),
+                Rectangle::new(0, 310, 500, 300),
+                Rectangle::new(510, 310, 500, 300),
+            ]
+        );
+    }
+
+    #[test]
+    fn last_row_shares_width() {
+        let area = Rectangle::new(0, 0, 1000, 600);
+        assert_eq!(
+            GridLayout {}.get_geometries(area, &config(0), 3),
+            vec![
+                Rectangle::new(0, 0, 500, 300),
+                Rectangle::new(500, 0, 500, 300),
+                Rectangle::new(0, 300, 1000, 300),
+            ]
+        );
+    }
+}
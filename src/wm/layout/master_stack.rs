@@ -0,0 +1,156 @@
+//! Master-stack tiling layout.
+
+use super::Layout;
+use crate::prelude::*;
+use crate::wm::client::ClientWindow;
+use crate::wm::config::LayoutConfig;
+use crate::wm::geometry::Rectangle;
+
+/// Tiles the first client in a master area on the left, and stacks
+/// the rest vertically on the right.
+pub struct MasterStackLayout {
+    /// Fraction of the available width occupied by the master area.
+    ratio: f32,
+}
+
+impl MasterStackLayout {
+    /// Smallest fraction of the available width the master area can occupy.
+    const MIN_RATIO: f32 = 0.05;
+    /// Largest fraction of the available width the master area can occupy.
+    const MAX_RATIO: f32 = 0.95;
+
+    #[must_use]
+    pub fn new(ratio: f32) -> Self {
+        Self {
+            ratio: ratio.clamp(Self::MIN_RATIO, Self::MAX_RATIO),
+        }
+    }
+}
+
+impl MasterStackLayout {
+    /// Compute the geometry of `count` clients inside `area`, in stack order.
+    ///
+    /// This doesn't touch the X server, so it can be used without a connection.
+    pub fn get_geometries(
+        &self,
+        area: Rectangle,
+        config: &LayoutConfig,
+        count: usize,
+    ) -> Vec<Rectangle> {
+        let area = area.inset(config.get_outer_gap());
+        let gap = config.get_gap_size();
+
+        match count {
+            0 => return vec![],
+            1 => return vec![area],
+            _ => {}
+        }
+
+        let (master_area, stack_area) = area.split_horizontal(self.ratio, gap);
+        // Computed in 32 bits, since large gaps between many clients can take
+        // more space than the stack has.
+        let (gap, area_height) = (u32::from(gap), u32::from(stack_area.height));
+        let stack_count = count as u32 - 1;
+        let gaps = gap * (stack_count - 1);
+        let stack_height = area_height.saturating_sub(gaps) / stack_count;
+
+        let mut geometries = vec![master_area];
+        for i in 0..stack_count {
+            let offset = ((stack_height + gap) * i).min(area_height);
+            // Let the last client take up any remaining space.
+            let height = if i == stack_count - 1 {
+                area_height - offset
+            } else {
+                stack_height
+            };
+            geometries.push(Rectangle::new(
+                stack_area.x,
+                stack_area.y.saturating_add(offset as i16),
+                stack_area.width,
+                height as u16,
+            ));
+        }
+        geometries
+    }
+}
+
+impl Default for MasterStackLayout {
+    fn default() -> Self {
+        Self::new(0.5)
+    }
+}
+
+impl Layout for MasterStackLayout {
+    fn get_name(&self) -> &'static str {
+        "master-stack"
+    }
+
+    fn configure(
+        &self,
+        _: &xcb::Connection,
+        area: Rectangle,
+        config: &LayoutConfig,
+        clients: &[ClientWindow],
+    ) -> NerdResult<Vec<(xcb::Window, Rectangle)>> {
+        let geometries = self.get_geometries(area, config, clients.len());
+        Ok(clients
+            .iter()
+            .map(|c| c.get_window())
+            .zip(geometries)
+            .collect())
+    }
+
+    fn adjust_master(&mut self, delta: f32) -> bool {
+        self.ratio = (self.ratio + delta).clamp(Self::MIN_RATIO, Self::MAX_RATIO);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(gap_size: u16) -> LayoutConfig {
+        toml::from_str(&format!("gap_size = {}", gap_size)).unwrap()
+    }
+
+    #[test]
+    fn three_windows() {
+        let layout = MasterStackLayout::default();
+        let area = Rectangle::new(0, 0, 1000, 600);
+        assert_eq!(
+            layout.get_geometries(area, &config(0), 3),
+            vec![
+                Rectangle::new(0, 0, 500, 600),
+                Rectangle::new(500, 0, 500, 300),
+                Rectangle::new(500, 300, 500, 300),
+            ]
+        );
+    }
+
+    #[test]
+    fn three_windows_with_gap() {
+        let layout = MasterStackLayout::default();
+        let area = Rectangle::new(0, 0, 1010, 610);
+        assert_eq!(
+            layout.get_geometries(area, &config(10), 3),
+            vec![
+                Rectangle::new(0, 0, 500, 610),
+                Rectangle::new(510, 0, 500, 300),
+                Rectangle::new(510, 310, 500, 300),
+            ]
+        );
+    }
+
+    #[test]
+    fn gaps_larger_than_stack() {
+        let layout = MasterStackLayout::default();
+        let area = Rectangle::new(0, 0, 1000, 600);
+        let geometries = layout.get_geometries(area, &config(u16::MAX / 2), 5);
+        assert_eq!(geometries.len(), 5);
+        for geometry in &geometries[1..] {
+            assert!(geometry.y >= 0);
+            assert!(geometry.y as i32 + geometry.height as i32 <= 600);
+        }
+    }
+}
@@ -1,16 +1,182 @@
 //! Provides algorithms for configuring window geometry.
+//!
+//! Tiling layouts compute geometry in a `get_geometries` method that doesn't
+//! touch the X server, and [`Layout::configure`] only applies its result. The
+//! geometry is unit tested through that method, while configuring clients
+//! needs a live server and is covered by the `xephyr` integration test.
 
+use super::client::ClientWindow;
+use super::config::LayoutConfig;
+use super::ewmh::EWMHManager;
+use super::geometry::Rectangle;
 use crate::prelude::*;
 
+mod bsp;
+mod grid;
+mod master_stack;
+mod monocle;
+mod tabbed;
+
+pub use bsp::BspLayout;
+pub use grid::GridLayout;
+pub use master_stack::MasterStackLayout;
+pub use monocle::MonocleLayout;
+pub use tabbed::TabbedLayout;
+
+/// Names of the layouts that can be created with [`layout_from_name`].
+pub const NAMES: &[&str] = &[
+    "master-stack",
+    "monocle",
+    "grid",
+    "tabbed",
+    "bsp",
+    "floating",
+    "blank",
+];
+
+/// Names of the layouts desktops cycle through if the configuration doesn't
+/// list any.
+pub const DEFAULT_NAMES: &[&str] = &["master-stack", "tabbed", "bsp", "blank"];
+
+/// Create a layout from its name in the configuration.
+///
+/// Layouts drawing their own windows create them as children of `root`.
+/// `floating` is a [`BlankLayout`] under another name, as it leaves every
+/// client where it is. Unknown names fall back to a [`BlankLayout`].
+pub fn layout_from_name(
+    name: &str,
+    conn: &xcb::Connection,
+    root: xcb::Window,
+    config: &LayoutConfig,
+) -> NerdResult<Box<dyn Layout>> {
+    Ok(match name {
+        "master-stack" => Box::new(MasterStackLayout::default()),
+        "monocle" => Box::new(MonocleLayout {}),
+        "grid" => Box::new(GridLayout {}),
+        "tabbed" => Box::new(TabbedLayout::new(conn, root, config.get_titlebar())?),
+        "bsp" => Box::new(BspLayout::new()),
+        "floating" => Box::new(BlankLayout::new("floating")),
+        "blank" => Box::new(BlankLayout::default()),
+        name => {
+            warn!("Unknown layout {:?}, using a blank layout", name);
+            Box::new(BlankLayout::default())
+        }
+    })
+}
+
 pub trait Layout {
-    fn configure(&self, clients: &[xcb::Window]) -> NerdResult<()>;
+    /// Get the name of the layout, as used in the configuration.
+    fn get_name(&self) -> &'static str;
+
+    /// Arrange `clients` to fit inside `area`, returning the geometry each
+    /// client window should be given.
+    ///
+    /// `area` is the rectangle that is available for tiling.
+    /// This excludes any space reserved by docks and panels. Layouts should
+    /// keep clients apart by the gaps in `config`.
+    ///
+    /// Clients are left for the caller to move, but anything drawn by the
+    /// layout itself, like a tab strip, is configured here.
+    fn configure(
+        &self,
+        conn: &xcb::Connection,
+        area: Rectangle,
+        config: &LayoutConfig,
+        clients: &[ClientWindow],
+    ) -> NerdResult<Vec<(xcb::Window, Rectangle)>>;
+
+    /// Grow the master area by `delta`, as a fraction of the available area.
+    ///
+    /// Returns `false` if this layout has no master area.
+    fn adjust_master(&mut self, _delta: f32) -> bool {
+        false
+    }
+
+    /// Switch the orientation of the split holding the focused client.
+    ///
+    /// Returns `false` if this layout has no splits.
+    fn rotate(&mut self) -> bool {
+        false
+    }
+
+    /// Swap the two sides of the split holding the focused client.
+    ///
+    /// Returns `false` if this layout has no splits.
+    fn flip(&mut self) -> bool {
+        false
+    }
+
+    /// Called when a client gets input focus. The client may not have
+    /// been configured by this layout yet.
+    fn focus(&self, _window: xcb::Window) {}
+
+    /// Check if `window` is drawn by this layout, like a tab strip.
+    fn owns(&self, _window: xcb::Window) -> bool {
+        false
+    }
+
+    /// Redraw anything drawn by this layout, for the `clients` it last configured.
+    fn draw(
+        &self,
+        _conn: &xcb::Connection,
+        _ewmh_mgr: &EWMHManager,
+        _clients: &[ClientWindow],
+        _focused: Option<xcb::Window>,
+    ) -> NerdResult<()> {
+        Ok(())
+    }
+
+    /// Get the index of the client drawn at `(x, y)`, relative to `window`.
+    ///
+    /// Only windows owned by the layout contain clients.
+    fn get_client_at(&self, _window: xcb::Window, _x: i16, _y: i16) -> Option<usize> {
+        None
+    }
+
+    /// Hide anything drawn by this layout. It is shown again the next
+    /// time the layout is configured.
+    fn hide(&self, _conn: &xcb::Connection) -> NerdResult<()> {
+        Ok(())
+    }
+
+    /// Free anything the layout created on the server, like a tab strip.
+    /// The layout must not be used after this.
+    fn destroy(&self, _conn: &xcb::Connection) -> NerdResult<()> {
+        Ok(())
+    }
+}
+
+/// A layout that does nothing, leaving every client where it is.
+pub struct BlankLayout {
+    /// Name of the layout, which is either `blank` or `floating`.
+    name: &'static str,
 }
 
-/// A layout that does nothing.
-pub struct BlankLayout {}
+impl BlankLayout {
+    #[must_use]
+    pub fn new(name: &'static str) -> Self {
+        Self { name }
+    }
+}
+
+impl Default for BlankLayout {
+    fn default() -> Self {
+        Self::new("blank")
+    }
+}
 
 impl Layout for BlankLayout {
-    fn configure(&self, _: &[xcb::Window]) -> NerdResult<()> {
-        Ok(())
+    fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    fn configure(
+        &self,
+        _: &xcb::Connection,
+        _: Rectangle,
+        _: &LayoutConfig,
+        _: &[ClientWindow],
+    ) -> NerdResult<Vec<(xcb::Window, Rectangle)>> {
+        Ok(vec![])
     }
 }
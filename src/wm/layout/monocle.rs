@@ -0,0 +1,30 @@
+//! Monocle layout.
+
+use super::Layout;
+use crate::prelude::*;
+use crate::wm::client::ClientWindow;
+use crate::wm::config::LayoutConfig;
+use crate::wm::geometry::Rectangle;
+
+/// Gives every client the whole area.
+///
+/// Only the focused client is visible, since focusing a client raises it
+/// above the others. Unlike [`super::TabbedLayout`], nothing else is drawn.
+pub struct MonocleLayout {}
+
+impl Layout for MonocleLayout {
+    fn get_name(&self) -> &'static str {
+        "monocle"
+    }
+
+    fn configure(
+        &self,
+        _: &xcb::Connection,
+        area: Rectangle,
+        config: &LayoutConfig,
+        clients: &[ClientWindow],
+    ) -> NerdResult<Vec<(xcb::Window, Rectangle)>> {
+        let area = area.inset(config.get_outer_gap());
+        Ok(clients.iter().map(|c| (c.get_window(), area)).collect())
+    }
+}
@@ -0,0 +1,236 @@
+//! Tabbed layout.
+
+use std::cell::Cell;
+
+use super::Layout;
+use crate::prelude::*;
+use crate::wm::client::{self, ClientWindow};
+use crate::wm::config::{LayoutConfig, TitlebarConfig};
+use crate::wm::ewmh::EWMHManager;
+use crate::wm::geometry::Rectangle;
+
+/// Gives every client the whole area, below a strip with a tab for each client.
+///
+/// Only the focused client is visible, since focusing a client raises it
+/// above the others. Clicking a tab focuses its client.
+pub struct TabbedLayout {
+    /// Window the tabs are drawn on.
+    strip: xcb::Window,
+    /// Graphics context holding the font and colors used to draw the tabs.
+    gc: xcb::Gcontext,
+    height: u16,
+    /// Vertical position of the text baseline.
+    baseline: i16,
+    foreground: u32,
+    background: u32,
+    /// Width of the strip and the number of tabs on it, as of the last
+    /// call to [`Layout::configure`].
+    tabs: Cell<(u16, usize)>,
+}
+
+impl TabbedLayout {
+    /// Height of the strip if titlebars are disabled.
+    const DEFAULT_HEIGHT: u16 = 18;
+
+    /// Create the tab strip as a child of `root`.
+    ///
+    /// The tabs use the font and colors of titlebars. The strip is only
+    /// mapped while there are clients to show.
+    pub fn new(
+        conn: &xcb::Connection,
+        root: xcb::Window,
+        config: &TitlebarConfig,
+    ) -> NerdResult<Self> {
+        let height = if config.is_enabled() {
+            config.get_height()
+        } else {
+            Self::DEFAULT_HEIGHT
+        };
+
+        let strip = conn.generate_id();
+        xcb::create_window_checked(
+            conn,
+            xcb::COPY_FROM_PARENT as u8,
+            strip,
+            root,
+            0,
+            0,
+            1,
+            height,
+            0,
+            xcb::WINDOW_CLASS_INPUT_OUTPUT as u16,
+            xcb::COPY_FROM_PARENT,
+            &[
+                (xcb::CW_BACK_PIXEL, config.get_background()),
+                // The strip belongs to the window manager, so it must never be managed.
+                (xcb::CW_OVERRIDE_REDIRECT, 1),
+                (
+                    xcb::CW_EVENT_MASK,
+                    xcb::EVENT_MASK_EXPOSURE | xcb::EVENT_MASK_BUTTON_PRESS,
+                ),
+            ],
+        )
+        .request_check()?;
+
+        let (gc, baseline) = client::create_text_gc(conn, strip, config, height)?;
+        Ok(Self {
+            strip,
+            gc,
+            height,
+            baseline,
+            foreground: config.get_foreground(),
+            background: config.get_background(),
+            tabs: Cell::new((0, 0)),
+        })
+    }
+
+    /// Get the area covered by the tab at `index`, relative to the strip.
+    fn get_tab_rect(&self, index: usize) -> xcb::Rectangle {
+        let (width, count) = self.tabs.get();
+        let start = width as usize * index / count.max(1);
+        let end = width as usize * (index + 1) / count.max(1);
+        xcb::Rectangle::new(start as i16, 0, (end - start) as u16, self.height)
+    }
+}
+
+impl Layout for TabbedLayout {
+    fn get_name(&self) -> &'static str {
+        "tabbed"
+    }
+
+    fn configure(
+        &self,
+        conn: &xcb::Connection,
+        area: Rectangle,
+        config: &LayoutConfig,
+        clients: &[ClientWindow],
+    ) -> NerdResult<Vec<(xcb::Window, Rectangle)>> {
+        let Rectangle {
+            x,
+            y,
+            width,
+            height,
+        } = area.inset(config.get_outer_gap());
+        self.tabs.set((width, clients.len()));
+
+        if clients.is_empty() {
+            self.hide(conn)?;
+            return Ok(vec![]);
+        }
+
+        xcb::configure_window_checked(
+            conn,
+            self.strip,
+            &[
+                (xcb::CONFIG_WINDOW_X as u16, x as u32),
+                (xcb::CONFIG_WINDOW_Y as u16, y as u32),
+                (xcb::CONFIG_WINDOW_WIDTH as u16, width.max(1) as u32),
+            ],
+        )
+        .request_check()?;
+        xcb::map_window_checked(conn, self.strip).request_check()?;
+
+        let geometry = Rectangle::new(
+            x,
+            y + self.height as i16,
+            width,
+            height.saturating_sub(self.height),
+        );
+        Ok(clients.iter().map(|c| (c.get_window(), geometry)).collect())
+    }
+
+    fn owns(&self, window: xcb::Window) -> bool {
+        window == self.strip
+    }
+
+    fn draw(
+        &self,
+        conn: &xcb::Connection,
+        ewmh_mgr: &EWMHManager,
+        clients: &[ClientWindow],
+        focused: Option<xcb::Window>,
+    ) -> NerdResult<()> {
+        if clients.is_empty() {
+            return Ok(());
+        }
+
+        xcb::clear_area_checked(conn, false, self.strip, 0, 0, 0, 0).request_check()?;
+
+        for (index, client) in clients.iter().enumerate() {
+            let rect = self.get_tab_rect(index);
+            let title = ewmh_mgr.get_name(client.get_window())?.unwrap_or_default();
+            let title = client::sanitize_title(&title);
+
+            // The focused tab is drawn in inverted colors.
+            let (foreground, background) = if focused == Some(client.get_window()) {
+                (self.background, self.foreground)
+            } else {
+                (self.foreground, self.background)
+            };
+
+            xcb::change_gc_checked(conn, self.gc, &[(xcb::GC_FOREGROUND, background)])
+                .request_check()?;
+            xcb::poly_fill_rectangle_checked(conn, self.strip, self.gc, &[rect]).request_check()?;
+
+            // Keep long titles from spilling into the next tab.
+            xcb::change_gc_checked(
+                conn,
+                self.gc,
+                &[
+                    (xcb::GC_FOREGROUND, foreground),
+                    (xcb::GC_BACKGROUND, background),
+                ],
+            )
+            .request_check()?;
+            xcb::set_clip_rectangles_checked(
+                conn,
+                xcb::CLIP_ORDERING_UNSORTED as u8,
+                self.gc,
+                0,
+                0,
+                &[rect],
+            )
+            .request_check()?;
+            xcb::image_text_8_checked(
+                conn,
+                self.strip,
+                self.gc,
+                rect.x() + 4,
+                self.baseline,
+                &title,
+            )
+            .request_check()?;
+        }
+
+        xcb::change_gc_checked(
+            conn,
+            self.gc,
+            &[
+                (xcb::GC_FOREGROUND, self.foreground),
+                (xcb::GC_BACKGROUND, self.background),
+                (xcb::GC_CLIP_MASK, xcb::NONE),
+            ],
+        )
+        .request_check()?;
+        Ok(())
+    }
+
+    fn get_client_at(&self, window: xcb::Window, x: i16, _y: i16) -> Option<usize> {
+        let (width, count) = self.tabs.get();
+        if window != self.strip || count == 0 || width == 0 || x < 0 {
+            return None;
+        }
+        Some((x as usize * count / width as usize).min(count - 1))
+    }
+
+    fn hide(&self, conn: &xcb::Connection) -> NerdResult<()> {
+        xcb::unmap_window_checked(conn, self.strip).request_check()?;
+        Ok(())
+    }
+
+    fn destroy(&self, conn: &xcb::Connection) -> NerdResult<()> {
+        xcb::free_gc_checked(conn, self.gc).request_check()?;
+        xcb::destroy_window_checked(conn, self.strip).request_check()?;
+        Ok(())
+    }
+}
@@ -1,100 +1,713 @@
 //! Window manager implementation.
 
+use std::collections::HashMap;
+use std::os::unix::process::CommandExt;
 use std::sync::Arc;
 
+use tokio::signal::unix::{signal, Signal, SignalKind};
+use tokio::time::Instant;
+
 use crate::events;
 use crate::prelude::*;
 
 pub mod actions;
+pub mod animation;
+pub mod client;
 pub mod config;
+pub mod cursor;
 pub mod desktop;
 pub mod ewmh;
+pub mod geometry;
+pub mod ipc;
 pub mod layout;
+pub mod monitor;
+pub mod scratchpad;
+pub mod snapshot;
+pub mod stack;
+pub mod state;
 
 use actions::{Action, ActionType};
+use client::{ClientWindow, TitlebarButton};
 use events::Event;
+use geometry::Rectangle;
 
 /// The "state" of the window manager. Processing of
 /// events will depend on this.
 #[derive(Debug, PartialEq, Eq)]
-#[allow(unused)]
 enum Mode {
     None,
-    MovingWindow,
-    ResizingWindow,
+    /// Moving a window, until the button that started the move is released.
+    MovingWindow(u8),
+    /// Resizing a window, until the button that started the resize is released.
+    ResizingWindow(u8),
+}
+
+/// Key sequence that has been started, but not finished.
+struct PendingChord {
+    /// Symbols and modifier states of the keys pressed so far.
+    keys: Vec<(xcb::Keysym, u32)>,
+    /// When the sequence is given up on if no other key is pressed.
+    deadline: Instant,
 }
 
 /// The window manager itself. This will keep track of virtual desktops and handle events.
 pub struct WindowManager {
     /// X server connection handle.
     conn: Arc<xcb::Connection>,
+    /// Index of the screen being managed, as preferred by `$DISPLAY`.
+    screen: usize,
     /// Helper for EWMH and atoms.
     ewmh_mgr: Arc<ewmh::EWMHManager>,
     /// Helper for event processing.
     event_mgr: events::EventManager,
-    /// Virtual desktops.
-    desktops: Vec<desktop::Desktop>,
+    /// Monitors, each with its own virtual desktops.
+    monitors: Vec<monitor::Monitor>,
+    /// Index of the monitor with focus. Actions that don't target a
+    /// particular window are performed on its active desktop.
+    focused_monitor: usize,
+    /// Docks and panels. These are shown on every desktop and are
+    /// never managed by a layout.
+    docks: Vec<xcb::Window>,
+    /// Window shown and hidden with [`ActionType::ToggleScratchpad`],
+    /// independently of desktops.
+    scratchpad: scratchpad::Scratchpad,
     /// Global configurations.
     config: config::Config,
+    /// Control socket. This is [`None`] if the socket could not be created.
+    ipc: Option<ipc::IpcServer>,
+    /// Stream of `SIGUSR1` signals, which trigger a config reload.
+    reload_signal: Signal,
+    /// Set to `false` to stop the event loop.
+    running: bool,
     /// Global mode. For some events, the action executed
     /// depends on the previous event, such as resizing a window.
     /// Moving the pointer will cause the window to be resized
     /// *only* if the previous event started the resizing action.
     mode: Mode,
+    /// Keycode and time of a bound modifier press that may still become a tap.
+    /// Pressing anything else before it is released clears this.
+    tap: Option<(xcb::Keycode, xcb::Timestamp)>,
+    /// Key sequence in progress. The keyboard is grabbed while this is set.
+    chord: Option<PendingChord>,
+    /// Modifier keys held while cycling through the focus history. The keyboard
+    /// is grabbed while this is set, and releasing any of them ends the cycle.
+    history_keys: Option<Vec<xcb::Keycode>>,
+    /// Clients last written to `_NET_CLIENT_LIST`, in the order they were managed.
+    client_list: Vec<xcb::Window>,
+    /// Clients last written to `_NET_CLIENT_LIST_STACKING`.
+    client_stacking: Vec<xcb::Window>,
 }
 
 impl WindowManager {
+    /// Longest a bound modifier can be held, in milliseconds, for its release to count as a tap.
+    const TAP_TIMEOUT: xcb::Timestamp = 500;
+    /// Set in the environment of the instance started by [`WindowManager::restart`].
+    const RESTARTED_VAR: &'static str = "NERDWM_RESTARTED";
+    /// Most desktops a pager can ask for.
+    const MAX_DESKTOPS: usize = 32;
+
+    // The connection and EWMH manager are shared by the window manager, its
+    // desktops and the event manager, but never leave the thread running the
+    // event loop, so they don't need to be `Send` or `Sync`.
+    #[allow(clippy::arc_with_non_send_sync)]
     pub fn new() -> NerdResult<Self> {
         // Connect to the X server
-        let conn = Arc::new(xcb::Connection::connect(None)?.0);
-        let ewmh_mgr = Arc::new(ewmh::EWMHManager::new(conn.clone()));
+        let (conn, screen) = xcb::Connection::connect(None)?;
+        let screen = screen as usize;
+        let (root, geometries) = match conn.get_setup().roots().nth(screen) {
+            Some(s) => (s.root(), monitor::get_monitors(&conn, &s)),
+            None => return Err(Error::Other(format!("screen {} does not exist", screen))),
+        };
+
+        // Checked before anything else is created, so a second instance
+        // leaves the running one alone.
+        Self::select_root_events(&conn, root)?;
+
+        let conn = Arc::new(conn);
+        let ewmh_mgr = Arc::new(ewmh::EWMHManager::new(conn.clone(), screen));
 
         // TODO: accept absolute path as argument to read from, and generate non-existent configs.
-        let config = {
-            let config_str = include_str!("../../assets/config.toml");
-            config::Config::from_str(config_str)
-        };
+        let config = config::Config::load()?;
+
+        let mut desktops: Vec<Vec<desktop::Desktop>> = geometries.iter().map(|_| vec![]).collect();
+        for desktop_config in config.get_desktops() {
+            let name = desktop_config.get_name();
+            let mut monitor = desktop_config.get_monitor();
+            if monitor >= desktops.len() {
+                warn!(
+                    "Desktop {} is on monitor {}, which doesn't exist",
+                    name, monitor
+                );
+                monitor = desktops.len() - 1;
+            }
+
+            let mut desktop =
+                Self::create_desktop(&conn, root, name.to_owned(), &config, &ewmh_mgr)?;
+            if let Some(layout) = desktop_config.get_layout() {
+                if !desktop.set_layout(layout)? {
+                    warn!("Desktop {} has unknown layout {:?}", name, layout);
+                }
+            }
+            desktops[monitor].push(desktop);
+        }
+
+        let mut monitors = vec![];
+        for (i, (geometry, mut desktops)) in geometries.into_iter().zip(desktops).enumerate() {
+            if desktops.is_empty() {
+                let name = match i {
+                    0 => "main".to_owned(),
+                    i => format!("main-{}", i),
+                };
+                desktops.push(Self::create_desktop(&conn, root, name, &config, &ewmh_mgr)?);
+            }
+            monitors.push(monitor::Monitor::new(geometry, desktops));
+        }
 
         let mut wm = Self {
             conn: conn.clone(),
+            screen,
             ewmh_mgr: ewmh_mgr.clone(),
-            event_mgr: events::EventManager::new(conn.clone()),
+            event_mgr: events::EventManager::new(conn.clone())?,
             config,
+            ipc: None,
+            reload_signal: signal(SignalKind::user_defined1())?,
+            running: true,
             mode: Mode::None,
-            // TODO: read from config
-            desktops: vec![desktop::Desktop::new(
-                conn,
-                "main".to_owned(),
-                Box::new(layout::BlankLayout {}),
-                ewmh_mgr,
-            )],
+            tap: None,
+            chord: None,
+            history_keys: None,
+            client_list: vec![],
+            client_stacking: vec![],
+            monitors,
+            focused_monitor: 0,
+            docks: vec![],
+            scratchpad: scratchpad::Scratchpad::new(conn.clone()),
         };
 
+        let modifiers = wm.get_modifier_map();
+        wm.config.resolve_modifiers(&modifiers);
+
         wm.init()?;
         Ok(wm)
     }
 
+    /// Create a desktop with the configured layouts.
+    fn create_desktop(
+        conn: &Arc<xcb::Connection>,
+        root: xcb::Window,
+        name: String,
+        config: &config::Config,
+        ewmh_mgr: &Arc<ewmh::EWMHManager>,
+    ) -> NerdResult<desktop::Desktop> {
+        let layout_config = config.get_layout().clone();
+        let layouts = layout_config
+            .get_layouts()
+            .iter()
+            .map(|name| layout::layout_from_name(name, conn, root, &layout_config))
+            .collect::<NerdResult<_>>()?;
+        Ok(desktop::Desktop::new(
+            conn.clone(),
+            name,
+            layouts,
+            layout_config,
+            ewmh_mgr.clone(),
+        ))
+    }
+
+    /// Get the desktops of every monitor, in the order they are numbered.
+    fn get_desktops(&self) -> impl Iterator<Item = &desktop::Desktop> {
+        self.monitors.iter().flat_map(|m| m.get_desktops())
+    }
+
+    /// Update the number and names of desktops advertised to pagers.
+    fn update_desktop_names(&self) -> NerdResult<()> {
+        self.ewmh_mgr.update_desktops(
+            &self
+                .get_desktops()
+                .map(|d| &d.get_name()[..])
+                .collect::<Vec<&str>>()[..],
+        )
+    }
+
+    /// Add or remove desktops at the end of the last monitors until there
+    /// are `count` of them, as asked by a pager.
+    ///
+    /// Every monitor keeps at least one desktop, and there are never more than
+    /// [`WindowManager::MAX_DESKTOPS`]. Clients on a removed desktop are moved
+    /// to the last desktop left on its monitor.
+    fn set_desktop_count(&mut self, count: usize) -> NerdResult<()> {
+        let count = Self::clamp_desktop_count(count);
+        let root = self.get_root()?;
+        let mut total = self.get_desktops().count();
+        if count == total {
+            return Ok(());
+        }
+
+        while total < count {
+            total += 1;
+            let desktop = Self::create_desktop(
+                &self.conn,
+                root,
+                format!("desktop-{}", total),
+                &self.config,
+                &self.ewmh_mgr,
+            )?;
+            if let Some(monitor) = self.monitors.last_mut() {
+                monitor.push_desktop(desktop);
+            }
+        }
+
+        while total > count {
+            let monitor = match self
+                .monitors
+                .iter_mut()
+                .rev()
+                .find(|m| m.get_desktops().len() > 1)
+            {
+                Some(m) => m,
+                None => break,
+            };
+            if let Some(mut removed) = monitor.pop_desktop()? {
+                let clients = removed.take_clients()?;
+                removed.release_all()?;
+
+                let visible = monitor.get_active() == monitor.get_desktops().len() - 1;
+                if let Some(last) = monitor.get_desktops_mut().last_mut() {
+                    last.give_clients(clients, visible)?;
+                }
+            }
+            total -= 1;
+        }
+        info!("Changed the number of desktops to {}", total);
+
+        self.update_desktop_names()?;
+        self.ewmh_mgr
+            .update_current_desktop(self.get_current_desktop())?;
+        self.update_workarea()
+    }
+
+    /// Limit a requested number of desktops to [`WindowManager::MAX_DESKTOPS`].
+    fn clamp_desktop_count(count: usize) -> usize {
+        if count > Self::MAX_DESKTOPS {
+            warn!(
+                "Asked for {} desktops, using {} instead",
+                count,
+                Self::MAX_DESKTOPS
+            );
+        }
+        count.min(Self::MAX_DESKTOPS)
+    }
+
+    /// Get the desktops of every monitor mutably, in the order they are numbered.
+    fn get_desktops_mut(&mut self) -> impl Iterator<Item = &mut desktop::Desktop> {
+        self.monitors.iter_mut().flat_map(|m| m.get_desktops_mut())
+    }
+
+    /// Get the desktop shown on the focused monitor.
+    fn get_active_desktop_mut(&mut self) -> &mut desktop::Desktop {
+        self.monitors[self.focused_monitor].get_active_desktop_mut()
+    }
+
+    /// Find the monitor and the index within it of the desktop numbered `index`.
+    fn find_desktop(&self, mut index: usize) -> Option<(usize, usize)> {
+        for (m, monitor) in self.monitors.iter().enumerate() {
+            match monitor.get_desktops().len() {
+                len if index < len => return Some((m, index)),
+                len => index -= len,
+            }
+        }
+        None
+    }
+
+    /// Get the number of the desktop at `index` on the monitor `monitor`.
+    fn get_desktop_number(&self, monitor: usize, index: usize) -> usize {
+        self.monitors[..monitor]
+            .iter()
+            .map(|m| m.get_desktops().len())
+            .sum::<usize>()
+            + index
+    }
+
+    /// Get the number of the desktop shown on the focused monitor.
+    fn get_current_desktop(&self) -> usize {
+        let monitor = &self.monitors[self.focused_monitor];
+        self.get_desktop_number(self.focused_monitor, monitor.get_active())
+    }
+
+    /// Give focus to a monitor, and update the hint of the current desktop.
+    fn focus_monitor(&mut self, monitor: usize) -> NerdResult<()> {
+        if monitor != self.focused_monitor {
+            self.focused_monitor = monitor;
+            self.ewmh_mgr
+                .update_current_desktop(self.get_current_desktop())?;
+        }
+        Ok(())
+    }
+
+    /// Get the index of the monitor `offset` places away from the focused one,
+    /// wrapping around at either end.
+    fn get_monitor_by(&self, offset: isize) -> usize {
+        let len = self.monitors.len() as isize;
+        (self.focused_monitor as isize + offset).rem_euclid(len) as usize
+    }
+
+    /// Focus the monitor `offset` places away from the focused one, along with
+    /// the focused client on it.
+    ///
+    /// The pointer is moved to the center of the monitor, so that the monitor
+    /// under it is the one given focus.
+    fn focus_monitor_by(&mut self, offset: isize) -> NerdResult<()> {
+        let monitor = self.get_monitor_by(offset);
+        if monitor == self.focused_monitor {
+            return Ok(());
+        }
+        self.focus_monitor(monitor)?;
+
+        let (x, y) = self.monitors[monitor].get_geometry().center();
+        xcb::warp_pointer_checked(&self.conn, xcb::NONE, self.get_root()?, 0, 0, 0, 0, x, y)
+            .request_check()?;
+
+        let desktop = self.get_active_desktop_mut();
+        if let Some(window) = desktop.get_focused().map(ClientWindow::get_window) {
+            desktop.focus(window)?;
+        }
+        Ok(())
+    }
+
+    /// Move the focused client to the desktop shown on the monitor `offset`
+    /// places away from the focused one, and focus it there.
+    ///
+    /// Both desktops are rearranged. Floating clients keep their position
+    /// relative to the monitor.
+    fn move_to_monitor_by(&mut self, offset: isize) -> NerdResult<()> {
+        let (source, target) = (self.focused_monitor, self.get_monitor_by(offset));
+        if source == target {
+            return Ok(());
+        }
+
+        let desktop = self.monitors[source].get_active_desktop_mut();
+        let window = match desktop.get_focused() {
+            Some(client) => client.get_window(),
+            None => return Ok(()),
+        };
+        let geometry = desktop.get_focused().map(|c| c.get_geometry(&self.conn));
+        let (client, floating) = match desktop.take_client(window)? {
+            Some(taken) => taken,
+            None => return Ok(()),
+        };
+        trace!("Moving {} from monitor {} to {}", window, source, target);
+
+        let from = self.monitors[source].get_geometry();
+        let to = self.monitors[target].get_geometry();
+        let desktop = self.monitors[target].get_active_desktop_mut();
+        desktop.give_clients(vec![(client, floating)], true)?;
+        if let Some(geometry) = geometry.transpose()? {
+            desktop.place_floating(
+                window,
+                Rectangle::new(
+                    geometry.x - from.x + to.x,
+                    geometry.y - from.y + to.y,
+                    geometry.width,
+                    geometry.height,
+                ),
+            )?;
+        }
+
+        self.focus_monitor(target)?;
+        self.get_active_desktop_mut().focus(window)
+    }
+
+    /// Get the index of the monitor containing `(x, y)`, or the focused
+    /// monitor if none does.
+    fn get_monitor_at(&self, x: i16, y: i16) -> usize {
+        self.monitors
+            .iter()
+            .position(|m| m.get_geometry().contains(x, y))
+            .unwrap_or(self.focused_monitor)
+    }
+
+    /// Get the earliest time any visible desktop needs its animations stepped.
+    fn get_next_frame(&self) -> Option<Instant> {
+        self.monitors
+            .iter()
+            .filter_map(|m| m.get_active_desktop().get_next_frame())
+            .min()
+    }
+
+    /// Get when the next client that was asked to close should be killed, on any desktop.
+    fn get_next_kill(&self) -> Option<Instant> {
+        self.get_desktops()
+            .filter_map(desktop::Desktop::get_next_kill)
+            .min()
+    }
+
     /// Runs the event loop.
+    ///
+    /// The loop never blocks on the X connection. Instead, it waits for either
+    /// the connection to become readable or a command from the control socket,
+    /// so other sources of work can be serviced in between X events.
     pub async fn run(&mut self) -> NerdResult<()> {
-        while self.conn.has_error().is_ok() {
+        while self.running {
+            self.handle_events()?;
+            self.update_client_list()?;
             self.conn.flush();
 
-            if let Some(action) = self.event_to_action(self.event_mgr.get_event()?) {
-                self.desktops[0].do_action(action)?;
+            if !self.running {
+                break;
+            }
+
+            let next_frame = self.get_next_frame();
+            let next_kill = self.get_next_kill();
+            tokio::select! {
+                res = self.event_mgr.wait_for_events() => res?,
+                Some(request) = Self::recv_command(&mut self.ipc) => match request {
+                    ipc::Request::Action(ty) => {
+                        Self::tolerate_bad_window(self.dispatch(Action::new(ty, Event::Unknown)))?;
+                    }
+                    ipc::Request::DumpState(reply) => {
+                        let response = self.snapshot().map(|s| s.to_json()).map_err(|e| e.to_string());
+                        // The client may have disconnected in the meantime.
+                        let _ = reply.send(response);
+                    }
+                },
+                Some(_) = self.reload_signal.recv() => {
+                    if let Err(e) = self.reload_config() {
+                        error!("Failed to reload config: {}", e);
+                    }
+                }
+                _ = Self::wait_until(self.chord.as_ref().map(|c| c.deadline)) => {
+                    info!("Key sequence timed out");
+                    self.abort_chord();
+                }
+                _ = Self::wait_until(next_frame) => {
+                    for monitor in &mut self.monitors {
+                        let result = monitor.get_active_desktop_mut().step_animations();
+                        // A failed frame is not worth exiting for.
+                        if let Err(e) = Self::tolerate_bad_window(result) {
+                            error!("Unable to animate clients: {}", e);
+                        }
+                    }
+                }
+                _ = Self::wait_until(next_kill) => {
+                    for desktop in self.get_desktops_mut() {
+                        if let Err(e) = desktop.kill_unresponsive() {
+                            error!("Unable to kill unresponsive clients: {}", e);
+                        }
+                    }
+                }
             }
         }
 
+        self.release_clients()
+    }
+
+    /// Give every client back to the root window before exiting.
+    ///
+    /// Clients get their original border back and are taken out of the save
+    /// set, so nothing is left behind for the next window manager to clean up.
+    fn release_clients(&mut self) -> NerdResult<()> {
+        for monitor in &mut self.monitors {
+            for desktop in monitor.get_desktops_mut() {
+                desktop.release_all()?;
+            }
+        }
+        self.scratchpad.release()?;
+        self.conn.flush();
         Ok(())
     }
 
-    /// Setup event masks, required atoms, and load configurations.
-    pub fn init(&mut self) -> NerdResult<()> {
-        let root = self.get_root()?;
+    /// Handle all events that have been received so far, in order.
+    ///
+    /// This includes events xcb queued internally while waiting for replies,
+    /// which won't make the connection readable again.
+    fn handle_events(&mut self) -> NerdResult<()> {
+        while let Some(event) = self.event_mgr.poll_event()? {
+            Self::tolerate_bad_window(self.handle_event(event))?;
 
-        // Capture events on root. All events/requests for any
-        // changes to its direct children can be captured and handled.
-        xcb::change_window_attributes(
-            &self.conn,
+            if !self.running {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle a single event.
+    fn handle_event(&mut self, event: Event) -> NerdResult<()> {
+        self.forget_dock(&event)?;
+        self.forget_client(&event)?;
+        self.redraw_title(&event)?;
+        self.property_changed(&event)?;
+        self.client_message(&event)?;
+        self.configure_request(&event)?;
+
+        if let Some(action) = self.event_to_action(event) {
+            self.dispatch(action)?;
+        }
+        Ok(())
+    }
+
+    /// Ignore errors caused by a client window being destroyed while it was
+    /// being handled. The window's `DestroyNotify` cleans up after it.
+    fn tolerate_bad_window(result: NerdResult<()>) -> NerdResult<()> {
+        match result {
+            Err(e) if e.is_bad_window() => {
+                debug!("Ignoring request on a destroyed window: {}", e);
+                Ok(())
+            }
+            res => res,
+        }
+    }
+
+    /// Find out which modifiers the `Alt`, `Meta`, `Super` and `Hyper` keys are
+    /// mapped to, falling back to the usual ones if the server can't be asked.
+    fn get_modifier_map(&self) -> events::input::ModifierMap {
+        events::input::ModifierMap::query(&self.conn, self.event_mgr.get_keysyms()).unwrap_or_else(
+            |e| {
+                warn!(
+                    "Unable to read the modifier mapping, assuming the usual one: {}",
+                    e
+                );
+                events::input::ModifierMap::default()
+            },
+        )
+    }
+
+    /// Take a snapshot of the monitors, desktops and clients.
+    fn snapshot(&self) -> NerdResult<snapshot::Snapshot> {
+        let mut index = 0;
+        let mut monitors = vec![];
+        for (m, monitor) in self.monitors.iter().enumerate() {
+            let mut desktops = vec![];
+            for desktop in monitor.get_desktops() {
+                let focused = desktop.get_focused().map(|c| c.get_window());
+                let mut clients = vec![];
+                for client in desktop.get_clients() {
+                    let window = client.get_window();
+                    clients.push(snapshot::ClientSnapshot::new(
+                        window,
+                        self.ewmh_mgr.get_name(window)?,
+                        self.ewmh_mgr.get_class(window)?.map(|(_, class)| class),
+                        client.get_geometry(&self.conn)?,
+                        desktop.is_floating(window),
+                        desktop.is_maximized(window),
+                        focused == Some(window),
+                    ));
+                }
+                desktops.push(snapshot::DesktopSnapshot::new(
+                    index,
+                    desktop.get_name().clone(),
+                    clients,
+                ));
+                index += 1;
+            }
+            monitors.push(snapshot::MonitorSnapshot::new(
+                monitor.get_geometry(),
+                monitor.get_active(),
+                m == self.focused_monitor,
+                desktops,
+            ));
+        }
+        Ok(snapshot::Snapshot::new(monitors))
+    }
+
+    /// Get the configuration in use.
+    pub fn get_config(&self) -> &config::Config {
+        &self.config
+    }
+
+    /// Get every client managed on any desktop, along with the index of its desktop.
+    pub fn all_clients(&self) -> Vec<(xcb::Window, usize)> {
+        self.get_desktops()
+            .enumerate()
+            .flat_map(|(index, desktop)| desktop.get_windows().into_iter().map(move |w| (w, index)))
+            .collect()
+    }
+
+    /// Get the clients of all desktops from bottom to top, as their frames
+    /// are stacked on the server.
+    fn get_stacking_order(&self) -> NerdResult<Vec<xcb::Window>> {
+        let frames: HashMap<_, _> = self
+            .get_desktops()
+            .flat_map(|d| d.get_clients())
+            .map(|c| (c.get_frame(), c.get_window()))
+            .collect();
+
+        let tree = xcb::query_tree(&self.conn, self.get_root()?).get_reply()?;
+        Ok(tree
+            .children()
+            .iter()
+            .filter_map(|f| frames.get(f).copied())
+            .collect())
+    }
+
+    /// Update `_NET_CLIENT_LIST` and `_NET_CLIENT_LIST_STACKING` with the
+    /// clients of all desktops, if they changed.
+    ///
+    /// `_NET_CLIENT_LIST` is in the order clients were managed, so clients
+    /// that are gone are dropped from it and new ones are added at the end.
+    /// The stacking order is only queried when clients were added, removed
+    /// or restacked.
+    fn update_client_list(&mut self) -> NerdResult<()> {
+        // Every desktop is checked, so none is left marked as restacked.
+        let restacked = self.get_desktops().filter(|d| d.take_restacked()).count() > 0;
+
+        let managed: Vec<_> = self.all_clients().into_iter().map(|(w, _)| w).collect();
+        let mut clients: Vec<_> = self
+            .client_list
+            .iter()
+            .filter(|w| managed.contains(w))
+            .copied()
+            .collect();
+        for window in managed {
+            if !clients.contains(&window) {
+                clients.push(window);
+            }
+        }
+        let changed = clients != self.client_list;
+        if changed {
+            self.ewmh_mgr.update_client_list(&clients[..])?;
+            self.client_list = clients;
+        }
+        if !changed && !restacked {
+            return Ok(());
+        }
+
+        let stacking = self.get_stacking_order()?;
+        if stacking != self.client_stacking {
+            self.ewmh_mgr.update_client_list_stacking(&stacking[..])?;
+            self.client_stacking = stacking;
+        }
+        Ok(())
+    }
+
+    /// Wait for the next command from the control socket.
+    ///
+    /// This never completes if the socket is unavailable.
+    async fn recv_command(ipc: &mut Option<ipc::IpcServer>) -> Option<ipc::Request> {
+        match ipc {
+            Some(ipc) => ipc.recv().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Wait until `deadline`.
+    ///
+    /// This never completes if there is no deadline.
+    async fn wait_until(deadline: Option<Instant>) {
+        match deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Capture events on root. All events/requests for any
+    /// changes to its direct children can be captured and handled.
+    ///
+    /// Only one client can select substructure redirection on the root,
+    /// so this fails if another window manager is running.
+    fn select_root_events(conn: &xcb::Connection, root: xcb::Window) -> NerdResult<()> {
+        xcb::change_window_attributes_checked(
+            conn,
             root,
             &[(
                 xcb::CW_EVENT_MASK,
@@ -102,55 +715,827 @@ impl WindowManager {
                     | xcb::EVENT_MASK_SUBSTRUCTURE_REDIRECT
                     | xcb::EVENT_MASK_BUTTON_PRESS
                     | xcb::EVENT_MASK_BUTTON_RELEASE
-                    | xcb::EVENT_MASK_POINTER_MOTION,
+                    | xcb::EVENT_MASK_POINTER_MOTION
+                    | xcb::EVENT_MASK_PROPERTY_CHANGE,
             )],
         )
-        .request_check()?;
+        .request_check()
+        .map_err(|e| match e {
+            xcb::ReplyError::GenericError(ref g) if g.error_code() == xcb::ACCESS => {
+                Error::Static("another window manager is already running")
+            }
+            e => e.into(),
+        })?;
+        Ok(())
+    }
+
+    /// Setup the control socket, required atoms, and load configurations.
+    pub fn init(&mut self) -> NerdResult<()> {
+        self.ipc = match ipc::IpcServer::bind() {
+            Ok(ipc) => Some(ipc),
+            Err(e) => {
+                warn!("Failed to create control socket: {}", e);
+                None
+            }
+        };
 
         // Setup EWMH hints
         // ----------------
         self.ewmh_mgr.set_supported()?;
         self.ewmh_mgr.set_pid()?;
-        self.ewmh_mgr.set_name("nerdwm")?;
+        self.ewmh_mgr.set_supporting_wm_check("nerdwm")?;
         self.ewmh_mgr.update_active_window(None)?;
-        self.ewmh_mgr.update_desktops(
-            &self
-                .desktops
-                .iter()
-                .map(|d| &d.get_name()[..])
-                .collect::<Vec<&str>>()[..],
-        )?;
+        self.ewmh_mgr.update_client_list(&[])?;
+        self.ewmh_mgr.update_client_list_stacking(&[])?;
+        self.update_desktop_names()?;
+
+        self.ewmh_mgr
+            .update_current_desktop(self.get_current_desktop())?;
+        self.update_workarea()?;
+        self.set_root_cursor();
+
+        self.conn.flush();
+
+        self.config.apply_log_level();
+        self.config.log();
+        self.grab_bindings()?;
+
+        // Commands spawned from here on inherit the environment, so they
+        // mustn't see this if they start another instance.
+        let restarted = std::env::var_os(Self::RESTARTED_VAR).is_some();
+        std::env::remove_var(Self::RESTARTED_VAR);
+        self.manage_existing(restarted)?;
+        self.autostart(restarted);
+
+        info!("Initialized!");
+        Ok(())
+    }
 
+    /// Run the configured autostart commands, unless this instance was started
+    /// by a restart and the commands already ran.
+    ///
+    /// Commands that can't be started are logged and skipped.
+    fn autostart(&self, restarted: bool) {
+        if restarted {
+            info!("Restarted, skipping autostart");
+            return;
+        }
+
+        for command in self.config.get_autostart() {
+            info!("Autostarting `{}`", command);
+            if let Err(e) = actions::spawn(command) {
+                error!("{}", e);
+            }
+        }
+    }
+
+    /// Manage the windows that were mapped before the window manager started.
+    ///
+    /// If `restarted`, windows left behind by the previous instance are put
+    /// back on the desktops they were on, and the state file is removed once
+    /// they are. Any other window is handled as if it had just asked to be mapped.
+    ///
+    /// Windows destroyed while this runs are skipped.
+    ///
+    /// Focus is then given back to the window that had it before, or else the
+    /// one under the pointer, so it doesn't jump when starting mid-session.
+    fn manage_existing(&mut self, restarted: bool) -> NerdResult<()> {
+        let saved = match restarted.then(state::SavedState::load) {
+            Some(Ok(s)) => s,
+            Some(Err(e)) => {
+                warn!("Unable to restore state: {}", e);
+                None
+            }
+            None => None,
+        };
+
+        let root = self.get_root()?;
+        let input_focus = xcb::get_input_focus(&self.conn).get_reply()?.focus();
+
+        let tree = xcb::query_tree(&self.conn, root).get_reply()?;
+        for &window in tree.children() {
+            Self::tolerate_bad_window(self.manage_existing_window(root, window, saved.as_ref()))?;
+        }
+
+        if saved.is_some() {
+            state::SavedState::remove()?;
+            info!("Restored state");
+        }
+
+        // Clients are framed by now, so the pointer is over a frame.
+        let (_, _, pointer) = self.event_mgr.query_pointer(root)?;
+        for window in [input_focus, pointer] {
+            if self.activate(window)? {
+                trace!("Focused {} after managing existing windows", window);
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Manage a window that was mapped before the window manager started,
+    /// putting it back where `saved` says it was if it is listed there.
+    ///
+    /// Windows that aren't viewable, and override-redirect windows, are ignored.
+    fn manage_existing_window(
+        &mut self,
+        root: xcb::Window,
+        window: xcb::Window,
+        saved: Option<&state::SavedState>,
+    ) -> NerdResult<()> {
+        let attributes = xcb::get_window_attributes(&self.conn, window).get_reply()?;
+        if attributes.override_redirect() || attributes.map_state() != xcb::MAP_STATE_VIEWABLE as u8
+        {
+            return Ok(());
+        }
+
+        match saved.and_then(|s| s.get(window)) {
+            Some(client) => self.restore_client(window, client),
+            None => {
+                let event = xcb::MapRequestEvent::new(root, window);
+                let action = Action::new(ActionType::WindowFocus, Event::WindowMapRequest(event));
+                self.dispatch(action)
+            }
+        }
+    }
+
+    /// Put a window back on the desktop it was on before a restart.
+    fn restore_client(
+        &mut self,
+        window: xcb::Window,
+        saved: &state::SavedClient,
+    ) -> NerdResult<()> {
+        let (monitor, index) = self.find_desktop(saved.get_desktop()).unwrap_or((
+            self.focused_monitor,
+            self.monitors[self.focused_monitor].get_active(),
+        ));
+
+        let monitor = &mut self.monitors[monitor];
+        let active = monitor.get_active();
+        let desktop = &mut monitor.get_desktops_mut()[index];
+        desktop.set_floating(window, saved.is_floating())?;
+        if index == active {
+            desktop.manage(window)?;
+        } else {
+            // The frame is mapped when the desktop is shown.
+            desktop.adopt(window)?;
+        }
+        if let Some(geometry) = saved.get_geometry() {
+            desktop.place_floating(window, geometry)?;
+        }
+        Ok(())
+    }
+
+    /// Save the state of all clients and replace this process with a new instance.
+    ///
+    /// Client windows are in the save set, so they survive the connection closing.
+    /// If the new instance can't be started, this one keeps running.
+    fn restart(&mut self) -> NerdResult<()> {
+        let mut saved = state::SavedState::new();
+        for (index, desktop) in self.get_desktops().enumerate() {
+            for client in desktop.get_clients() {
+                let window = client.get_window();
+                saved.push(state::SavedClient::new(
+                    window,
+                    index,
+                    desktop.is_floating(window),
+                    desktop.get_normal_geometry(window)?,
+                ));
+            }
+        }
+        if let Err(e) = saved.save() {
+            error!("Unable to save state, restarting without it: {}", e);
+        }
+
+        info!("Restarting");
         self.conn.flush();
 
-        // TODO: Get existing windows
+        let error = std::env::current_exe().map(|exe| {
+            std::process::Command::new(exe)
+                .args(std::env::args_os().skip(1))
+                .env(Self::RESTARTED_VAR, "1")
+                .exec()
+        });
+        // `exec` only returns if it failed.
+        match error {
+            Ok(e) | Err(e) => error!("Unable to restart: {}", e),
+        }
+        state::SavedState::remove()?;
+        Ok(())
+    }
+
+    /// Show the configured cursor over the root window.
+    ///
+    /// Failures are only logged, since the server's default cursor is still usable.
+    fn set_root_cursor(&self) {
+        let result = cursor::get_cursor_named(&self.conn, self.config.get_cursor()).and_then(|c| {
+            xcb::change_window_attributes_checked(
+                &self.conn,
+                self.get_root()?,
+                &[(xcb::CW_CURSOR, c)],
+            )
+            .request_check()?;
+            // The root window keeps its own reference to the cursor.
+            xcb::free_cursor(&self.conn, c);
+            Ok(())
+        });
+
+        if let Err(e) = result {
+            warn!("Unable to set cursor {:?}: {}", self.config.get_cursor(), e);
+        }
+    }
+
+    /// Grab all bindings in the current configuration.
+    fn grab_bindings(&self) -> NerdResult<()> {
         xcb::grab_server_checked(&self.conn).request_check()?;
 
-        // Grab bindings
+        // A binding that can't be grabbed shouldn't keep the rest from working.
         for action in self.config.get_actions() {
             if let Some(k) = action.get_keybind() {
-                let _ = self.grab_keybind(k);
+                if let Err(e) = self.grab_keybind(k) {
+                    warn!("Unable to bind {:?}: {}", action.get_type(), e);
+                }
             }
             if let Some(b) = action.get_mousebind() {
-                let _ = self.grab_mousebind(b);
+                if let Err(e) = self.grab_mousebind(b) {
+                    warn!("Unable to bind {:?}: {}", action.get_type(), e);
+                }
+            }
+            if let Some(t) = action.get_tapbind() {
+                if let Err(e) = self.grab_keybind(t) {
+                    warn!("Unable to bind {:?}: {}", action.get_type(), e);
+                }
             }
         }
 
         xcb::ungrab_server_checked(&self.conn).request_check()?;
         self.conn.flush();
+        Ok(())
+    }
 
-        info!("Initialized!");
+    /// Release all key and button grabs on the root window.
+    fn ungrab_bindings(&self) -> NerdResult<()> {
+        let root = self.get_root()?;
+        xcb::ungrab_key_checked(
+            &self.conn,
+            xcb::GRAB_ANY as u8,
+            root,
+            xcb::MOD_MASK_ANY as u16,
+        )
+        .request_check()?;
+        xcb::ungrab_button_checked(
+            &self.conn,
+            xcb::BUTTON_INDEX_ANY as u8,
+            root,
+            xcb::MOD_MASK_ANY as u16,
+        )
+        .request_check()?;
+        Ok(())
+    }
+
+    /// Re-read the configuration file and apply it.
+    ///
+    /// If the new configuration can't be loaded, an error is logged
+    /// and the current configuration is kept.
+    fn reload_config(&mut self) -> NerdResult<()> {
+        info!("Reloading config");
+
+        let mut config = match config::Config::load() {
+            Ok(c) => c,
+            Err(e) => {
+                error!("Failed to reload config, keeping the current one: {}", e);
+                return Ok(());
+            }
+        };
+        config.resolve_modifiers(&self.get_modifier_map());
+
+        self.ungrab_bindings()?;
+        self.config = config;
+        self.mode = Mode::None;
+        self.tap = None;
+        self.abort_chord();
+        self.commit_focus_history()?;
+        self.config.apply_log_level();
+        self.config.log();
+        self.grab_bindings()?;
+
+        let layout = self.config.get_layout().clone();
+        for desktop in self.get_desktops_mut() {
+            desktop.set_config(layout.clone())?;
+        }
+        self.set_root_cursor();
+
+        info!("Config reloaded");
+        Ok(())
+    }
+
+    /// Pass an action on to the desktop it should be performed on.
+    ///
+    /// Newly mapped windows are matched against the configured rules first,
+    /// which may place them on another desktop or keep them out of the layout.
+    /// Docks are mapped directly and never handed to a desktop. Dialogs, utility
+    /// windows, toolbars and splash screens are kept floating at their requested
+    /// geometry, or centered if they didn't ask for a position.
+    /// Windows transient for another client are kept floating on its desktop,
+    /// centered on it.
+    /// Windows that are already managed are focused instead of being managed again.
+    fn dispatch(&mut self, action: Action) -> NerdResult<()> {
+        match action.get_type() {
+            ActionType::SwitchDesktop(index) => return self.switch_desktop(index),
+            ActionType::Spawn => {
+                match action.get_command() {
+                    Some(command) => {
+                        if let Err(e) = actions::spawn(command) {
+                            error!("{}", e);
+                        }
+                    }
+                    None => warn!("Spawn action has no command"),
+                }
+                return Ok(());
+            }
+            ActionType::ToggleScratchpad => return self.toggle_scratchpad(),
+            ActionType::FocusMonitorNext => return self.focus_monitor_by(1),
+            ActionType::FocusMonitorPrev => return self.focus_monitor_by(-1),
+            ActionType::MoveWindowToMonitorNext => return self.move_to_monitor_by(1),
+            ActionType::MoveWindowToMonitorPrev => return self.move_to_monitor_by(-1),
+            ActionType::FocusMruNext => return self.cycle_focus_history(action.get_event()),
+            ActionType::FocusMruCommit => return self.commit_focus_history(),
+            ActionType::WindowManagerQuit => {
+                info!("Quitting");
+                self.running = false;
+                return Ok(());
+            }
+            ActionType::WindowManagerRestart => return self.restart(),
+            _ => {}
+        }
+
+        if let Event::WindowMapRequest(e) = action.get_event() {
+            let window = e.window();
+
+            if desktop::is_override_redirect(&self.conn, window)? {
+                trace!("Passing through override-redirect window {}", window);
+                return Ok(());
+            }
+
+            if self.ewmh_mgr.is_dock(window)? {
+                trace!("Mapping dock {}", window);
+                xcb::map_window_checked(&self.conn, window).request_check()?;
+                if !self.docks.contains(&window) {
+                    self.docks.push(window);
+                }
+                return self.update_workarea();
+            }
+
+            // A window may ask to be mapped again, such as when it was mapped
+            // while existing windows were being managed. Framing it twice would
+            // leave it on two desktops.
+            if self.scratchpad.get_client().map(ClientWindow::get_window) == Some(window) {
+                return Ok(());
+            }
+            if self.activate(window)? {
+                trace!("Focusing already managed window {}", window);
+                return Ok(());
+            }
+
+            if let Some(config) = self.config.get_scratchpad() {
+                if self.scratchpad.get_client().is_none() {
+                    if let Some((instance, class)) = self.ewmh_mgr.get_class(window)? {
+                        if config.matches(&instance, &class) {
+                            let area = self.monitors[self.focused_monitor].get_workarea();
+                            let layout = self.config.get_layout();
+                            self.scratchpad.adopt(window, layout, config, area)?;
+                            if let Some(client) = self.scratchpad.get_client() {
+                                let (left, right, top, bottom) = client.get_frame_extents();
+                                self.ewmh_mgr
+                                    .set_frame_extents(window, left, right, top, bottom)?;
+                            }
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+
+            // New windows go to the monitor under the pointer, while dialogs
+            // go to the desktop of the window they belong to.
+            let monitor = self.active_monitor()?;
+            let mut target = (monitor, self.monitors[monitor].get_active());
+            let mut floating = self.ewmh_mgr.is_floating_type(window)?;
+
+            let parent = self.ewmh_mgr.get_transient_for(window)?;
+            if let Some(found) = parent.and_then(|p| self.find_client(p)) {
+                target = found;
+                floating = true;
+            }
+
+            if let Some((instance, class)) = self.ewmh_mgr.get_class(window)? {
+                if let Some(rule) = self.config.match_rule(&instance, &class) {
+                    trace!(
+                        "Applying rule to window {} ({}, {})",
+                        window,
+                        instance,
+                        class
+                    );
+                    if let Some(d) = rule.get_desktop() {
+                        match self.find_desktop(d) {
+                            Some(t) => target = t,
+                            None => {
+                                warn!("Rule for {} refers to non-existent desktop {}", class, d)
+                            }
+                        }
+                    }
+                    floating |= rule.is_floating();
+                }
+            }
+
+            let (monitor, index) = target;
+            let placement = self.get_placement_area(monitor);
+            let active = self.monitors[monitor].get_active();
+            let desktop = &mut self.monitors[monitor].get_desktops_mut()[index];
+            desktop.set_floating(window, floating)?;
+            desktop.set_placement(placement);
+            if let Some(parent) = parent {
+                desktop.set_transient_for(window, parent);
+            }
+
+            if index != active {
+                // The window will be mapped when its desktop is shown.
+                return desktop.adopt(window);
+            }
+            if !desktop.wants_focus(window)? {
+                return desktop.do_action(action);
+            }
+            self.focus_monitor(monitor)?;
+        }
+
+        // Pointer actions are performed on the monitor they start on.
+        if let Event::ButtonPress(e) = action.get_event() {
+            let monitor = self.get_monitor_at(e.root_x(), e.root_y());
+            self.focus_monitor(monitor)?;
+        }
+
+        self.get_active_desktop_mut().do_action(action)
+    }
+
+    /// Focus the next client in the focus history of the active desktop.
+    ///
+    /// If the binding that triggered this has modifiers that are still held,
+    /// the keyboard is grabbed so their release can be seen, and the cycle
+    /// continues until then. Otherwise the cycle ends right away.
+    fn cycle_focus_history(&mut self, event: &Event) -> NerdResult<()> {
+        self.get_active_desktop_mut().cycle_focus_history()?;
+        if self.history_keys.is_some() {
+            return Ok(());
+        }
+
+        let state = match event {
+            Event::KeyPress(e) => e.base.state(),
+            _ => 0,
+        };
+        let slots = events::input::get_modifier_slots(&self.conn)?;
+        let keymap = xcb::query_keymap(&self.conn).get_reply()?;
+        let pressed = |k: &xcb::Keycode| keymap.keys()[*k as usize / 8] & (1 << (k % 8)) != 0;
+        let held: Vec<_> = slots
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| state & (1 << i) != 0)
+            .flat_map(|(_, keycodes)| keycodes.iter().copied())
+            .filter(pressed)
+            .collect();
+
+        // The modifiers may have been released before the keyboard was grabbed.
+        if held.is_empty() {
+            return self.commit_focus_history();
+        }
+        match self.grab_keyboard(self.get_root()?)? {
+            status if status == xcb::GRAB_STATUS_SUCCESS as u8 => {
+                self.history_keys = Some(held);
+                Ok(())
+            }
+            status => {
+                warn!(
+                    "Unable to grab the keyboard to cycle focus, status {}",
+                    status
+                );
+                self.commit_focus_history()
+            }
+        }
+    }
+
+    /// End the focus history cycle in progress, if any, and release the keyboard.
+    fn commit_focus_history(&mut self) -> NerdResult<()> {
+        if self.history_keys.take().is_some() {
+            self.ungrab_keyboard();
+        }
+        for desktop in self.get_desktops_mut() {
+            desktop.commit_focus_history()?;
+        }
+        Ok(())
+    }
+
+    /// Show the scratchpad if it is hidden, or hide it otherwise.
+    ///
+    /// The scratchpad's command is run if its window doesn't exist yet,
+    /// and the window is shown once it is mapped.
+    fn toggle_scratchpad(&mut self) -> NerdResult<()> {
+        let config = match self.config.get_scratchpad() {
+            Some(c) => c,
+            None => {
+                warn!("No scratchpad is configured");
+                return Ok(());
+            }
+        };
+
+        if self.scratchpad.get_client().is_none() {
+            if let Err(e) = actions::spawn(config.get_command()) {
+                error!("{}", e);
+            }
+            return Ok(());
+        }
+
+        if self.scratchpad.is_visible() {
+            self.scratchpad.hide()?;
+
+            // Give focus back to the active desktop.
+            let desktop = self.get_active_desktop_mut();
+            if let Some(window) = desktop.get_focused().map(|c| c.get_window()) {
+                desktop.focus(window)?;
+            }
+            Ok(())
+        } else {
+            let area = self.monitors[self.focused_monitor].get_workarea();
+            self.scratchpad.show(config, area)
+        }
+    }
+
+    /// Show the desktop at `index` on the focused monitor, hiding its active desktop.
+    ///
+    /// Desktops are counted from the first desktop of the focused monitor.
+    fn switch_desktop(&mut self, index: usize) -> NerdResult<()> {
+        if !self.monitors[self.focused_monitor].switch_desktop(index)? {
+            warn!("Cannot switch to non-existent desktop {}", index);
+            return Ok(());
+        }
+        self.ewmh_mgr
+            .update_current_desktop(self.get_current_desktop())
+    }
+
+    /// Stop reserving space for a dock once it is destroyed or unmapped.
+    fn forget_dock(&mut self, event: &Event) -> NerdResult<()> {
+        let window = match event {
+            Event::WindowDestroy(e) => e.window(),
+            Event::WindowUnmap(e) => e.window(),
+            _ => return Ok(()),
+        };
+
+        if let Some(p) = self.docks.iter().position(|d| d == &window) {
+            self.docks.remove(p);
+            self.update_workarea()?;
+        }
+        Ok(())
+    }
+
+    /// Stop managing a client once it is destroyed or unmapped.
+    ///
+    /// Clients may be owned by any desktop, not only the visible one,
+    /// or be the scratchpad.
+    fn forget_client(&mut self, event: &Event) -> NerdResult<()> {
+        let (window, destroyed) = match event {
+            Event::WindowDestroy(e) => (e.window(), true),
+            // Managed clients are children of their frames. Unmapping reported
+            // relative to the root comes from reparenting a window that was
+            // already mapped, when it is first managed.
+            Event::WindowUnmap(e) if e.event() == self.get_root()? => return Ok(()),
+            Event::WindowUnmap(e) => (e.window(), false),
+            _ => return Ok(()),
+        };
+
+        if self.scratchpad.forget(window, destroyed)? {
+            return Ok(());
+        }
+
+        for desktop in self.get_desktops_mut() {
+            if desktop.unmanage(window, destroyed)? {
+                trace!("Unmanaged window {}", window);
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Redraw a client's titlebar when it is exposed.
+    fn redraw_title(&self, event: &Event) -> NerdResult<()> {
+        let window = match event {
+            Event::WindowExpose(e) if e.count() == 0 => e.window(),
+            _ => return Ok(()),
+        };
+
+        for desktop in self.get_desktops() {
+            if desktop.redraw_title(window)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Let the desktop owning a client react to changes of the client's properties.
+    fn property_changed(&mut self, event: &Event) -> NerdResult<()> {
+        let e = match event {
+            Event::PropertyNotify(e) => e,
+            _ => return Ok(()),
+        };
+
+        for desktop in self.get_desktops_mut() {
+            if desktop.property_changed(e.base.window(), e.atom())? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Answer a window's request to move, resize or restack itself.
+    ///
+    /// Managed clients are left to the desktop owning them. Other windows,
+    /// like docks and windows that aren't mapped yet, are configured as
+    /// requested, forwarding only the fields set in the request's value mask.
+    fn configure_request(&mut self, event: &Event) -> NerdResult<()> {
+        let e = match event {
+            Event::WindowConfigureRequest(e) => e,
+            _ => return Ok(()),
+        };
+
+        if self.scratchpad.configure_request(e.window())? {
+            return Ok(());
+        }
+        for desktop in self.get_desktops() {
+            if desktop.configure_request(e)? {
+                return Ok(());
+            }
+        }
+
+        // Values must be given in the order of their bits in the mask.
+        let mask = e.value_mask();
+        let values = [
+            (xcb::CONFIG_WINDOW_X, e.x() as u32),
+            (xcb::CONFIG_WINDOW_Y, e.y() as u32),
+            (xcb::CONFIG_WINDOW_WIDTH, e.width() as u32),
+            (xcb::CONFIG_WINDOW_HEIGHT, e.height() as u32),
+            (xcb::CONFIG_WINDOW_BORDER_WIDTH, e.border_width() as u32),
+            (xcb::CONFIG_WINDOW_SIBLING, e.sibling()),
+            (xcb::CONFIG_WINDOW_STACK_MODE, e.stack_mode() as u32),
+        ]
+        .iter()
+        .filter(|(bit, _)| mask & *bit as u16 != 0)
+        .map(|&(bit, value)| (bit as u16, value))
+        .collect::<Vec<_>>();
+
+        trace!("Configuring unmanaged window {}", e.window());
+        xcb::configure_window_checked(&self.conn, e.window(), &values).request_check()?;
         Ok(())
     }
 
-    /// Get the default root window.
+    /// Handle requests clients send to the root window.
+    ///
+    /// State changes are handled by the desktop owning the client. Requests
+    /// to activate a client, like from a taskbar, focus its monitor and
+    /// switch it to the client's desktop.
+    fn client_message(&mut self, event: &Event) -> NerdResult<()> {
+        let e = match event {
+            Event::ClientMessage(e) if e.format() == 32 => e,
+            _ => return Ok(()),
+        };
+
+        if e.type_()
+            == self
+                .ewmh_mgr
+                .get_atom(ewmh::protocols::_NET_ACTIVE_WINDOW)?
+        {
+            self.activate(e.window())?;
+        } else if e.type_()
+            == self
+                .ewmh_mgr
+                .get_atom(ewmh::protocols::_NET_NUMBER_OF_DESKTOPS)?
+        {
+            self.set_desktop_count(e.data().data32()[0] as usize)?;
+        } else if e.type_() == self.ewmh_mgr.get_atom(ewmh::protocols::_NET_WM_STATE)? {
+            let data = e.data().data32();
+            for desktop in self.get_desktops_mut() {
+                if desktop.request_state(e.window(), data[0], &data[1..3])? {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Find the monitor and the index within it of the desktop managing a
+    /// client, given either the client window or its frame.
+    fn find_client(&self, window: xcb::Window) -> Option<(usize, usize)> {
+        self.monitors.iter().enumerate().find_map(|(m, monitor)| {
+            monitor
+                .get_desktops()
+                .iter()
+                .position(|d| d.contains(window))
+                .map(|d| (m, d))
+        })
+    }
+
+    /// Focus a client, given either the client window or its frame, along with
+    /// its monitor, switching that monitor to the client's desktop.
+    ///
+    /// Returns `false` if the window isn't managed by any desktop.
+    fn activate(&mut self, window: xcb::Window) -> NerdResult<bool> {
+        match self.find_client(window) {
+            Some((monitor, index)) => {
+                self.focus_monitor(monitor)?;
+                self.switch_desktop(index)?;
+                self.get_active_desktop_mut().focus(window)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Compute the area left for clients on each monitor, after subtracting
+    /// the space reserved by docks.
+    ///
+    /// Docks only reserve space on the monitors their struts overlap, so a
+    /// panel along the bottom of one monitor leaves the others alone.
+    fn get_workareas(&self) -> NerdResult<Vec<Rectangle>> {
+        let screen = self.get_screen()?;
+        let (width, height) = (
+            screen.width_in_pixels() as u32,
+            screen.height_in_pixels() as u32,
+        );
+
+        let mut struts = vec![];
+        for dock in &self.docks {
+            if let Some(strut) = self.ewmh_mgr.get_strut(*dock)? {
+                struts.push(strut);
+            }
+        }
+
+        Ok(self
+            .monitors
+            .iter()
+            .map(|monitor| {
+                let geometry = monitor.get_geometry();
+                // [left, right, top, bottom]
+                let mut reserved = [0u32; 4];
+                for strut in &struts {
+                    let depths = strut.get_reserved(geometry, width, height);
+                    for (r, d) in reserved.iter_mut().zip(depths) {
+                        *r = (*r).max(d);
+                    }
+                }
+                geometry.shrink(reserved)
+            })
+            .collect())
+    }
+
+    /// Recompute the work area of every monitor, update the `_NET_WORKAREA`
+    /// hint, and reconfigure all desktops to fit inside the work area of their
+    /// monitor.
+    fn update_workarea(&mut self) -> NerdResult<()> {
+        let areas = self.get_workareas()?;
+        for (monitor, area) in self.monitors.iter_mut().zip(&areas) {
+            monitor.set_workarea(*area)?;
+        }
+
+        let desktop_areas: Vec<Rectangle> = self
+            .monitors
+            .iter()
+            .flat_map(|m| vec![m.get_workarea(); m.get_desktops().len()])
+            .collect();
+        self.ewmh_mgr.update_workarea(&desktop_areas)
+    }
+
+    /// Get the root window of the managed screen.
     fn get_root(&self) -> NerdResult<xcb::Window> {
-        match self.conn.get_setup().roots().next() {
-            Some(root) => Ok(root.root()),
+        Ok(self.get_screen()?.root())
+    }
+
+    /// Get the screen being managed.
+    fn get_screen(&self) -> NerdResult<xcb::Screen<'_>> {
+        match self.conn.get_setup().roots().nth(self.screen) {
+            Some(screen) => Ok(screen),
             None => Err(Error::Static("root window not found")),
         }
     }
 
+    /// Get the index of the monitor under the pointer.
+    ///
+    /// This is the focused monitor if the pointer isn't on any of them.
+    pub fn active_monitor(&self) -> NerdResult<usize> {
+        let (x, y, _) = self.event_mgr.query_pointer(self.get_root()?)?;
+        Ok(self.get_monitor_at(x, y))
+    }
+
+    /// Get the area new floating windows are placed in on `monitor`, which is
+    /// the part of the monitor not reserved by docks.
+    fn get_placement_area(&self, monitor: usize) -> Rectangle {
+        self.monitors[monitor].get_workarea()
+    }
+
     /// Grab a keyboard binding.
     fn grab_keybind(&self, bind: &config::KeyBind) -> NerdResult<()> {
         if let Some(keycode) = self
@@ -198,53 +1583,270 @@ impl WindowManager {
         Ok(())
     }
 
+    /// Actively grab the keyboard, so every key event is reported to `window`,
+    /// such as for the rest of a key sequence.
+    ///
+    /// Returns the status of the grab, which is [`xcb::GRAB_STATUS_SUCCESS`]
+    /// unless, for example, another client already grabbed the keyboard
+    /// ([`xcb::GRAB_STATUS_ALREADY_GRABBED`]).
+    fn grab_keyboard(&self, window: xcb::Window) -> NerdResult<u8> {
+        let reply = xcb::grab_keyboard(
+            &self.conn,
+            false, // owner events
+            window,
+            xcb::CURRENT_TIME,
+            xcb::GRAB_MODE_ASYNC as u8, // pointer mode
+            xcb::GRAB_MODE_ASYNC as u8, // keyboard mode
+        )
+        .get_reply()?;
+        Ok(reply.status())
+    }
+
+    /// Release the keyboard grabbed with [`WindowManager::grab_keyboard`].
+    fn ungrab_keyboard(&self) {
+        xcb::ungrab_keyboard(&self.conn, xcb::CURRENT_TIME);
+        self.conn.flush();
+    }
+
+    /// Give up on the key sequence in progress, if any, and release the keyboard.
+    fn abort_chord(&mut self) {
+        if self.chord.take().is_some() {
+            self.ungrab_keyboard();
+        }
+    }
+
+    /// Check if bindings with `context` apply right now.
+    fn is_active(&self, context: config::ActionContext) -> bool {
+        let dragging = !matches!(self.mode, Mode::None);
+        let desktop = self.monitors[self.focused_monitor].get_active_desktop();
+        let floating = desktop
+            .get_focused()
+            .is_some_and(|c| desktop.is_floating(c.get_window()));
+
+        match context {
+            config::ActionContext::MoveMode => dragging,
+            _ if dragging => false,
+            config::ActionContext::Always => true,
+            config::ActionContext::Floating => floating,
+            config::ActionContext::Tiling => !floating,
+        }
+    }
+
+    /// Resolve a key press into the action it is bound to.
+    ///
+    /// If the key continues a key sequence without finishing it, the sequence
+    /// is kept pending until the next key press or the configured timeout.
+    /// A key that doesn't continue any sequence aborts the pending one and is
+    /// otherwise ignored.
+    fn key_to_action(
+        &mut self,
+        keysym: xcb::Keysym,
+        state: u32,
+        event: events::Event,
+    ) -> Option<actions::Action> {
+        // Modifiers have to be pressed on their own before the keys they modify.
+        if self.chord.is_some() && events::input::is_modifier(keysym) {
+            return None;
+        }
+
+        let mut keys = self.chord.take().map(|c| c.keys).unwrap_or_default();
+        let pending = !keys.is_empty();
+        keys.push((keysym, state));
+
+        let mut partial = false;
+        for action in self.config.get_actions() {
+            let bind = match action.get_keybind() {
+                Some(k) if self.is_active(action.get_context()) => k,
+                _ => continue,
+            };
+            let sequence = std::iter::once(bind).chain(bind.get_sequence());
+            if !sequence
+                .clone()
+                .zip(&keys)
+                .all(|(k, (keysym, state))| k.matches(*keysym, *state))
+            {
+                continue;
+            }
+
+            match sequence.count().cmp(&keys.len()) {
+                std::cmp::Ordering::Equal => {
+                    if pending {
+                        self.ungrab_keyboard();
+                    }
+                    let command = action.get_command().clone();
+                    return Some(Action::new(action.get_type(), event).with_command(command));
+                }
+                std::cmp::Ordering::Greater => partial = true,
+                std::cmp::Ordering::Less => {}
+            }
+        }
+
+        if partial {
+            if !pending {
+                match self.get_root().and_then(|root| self.grab_keyboard(root)) {
+                    Ok(status) if status == xcb::GRAB_STATUS_SUCCESS as u8 => {}
+                    Ok(status) => {
+                        warn!("Unable to start key sequence, grab status {}", status);
+                        return None;
+                    }
+                    Err(e) => {
+                        warn!("Unable to start key sequence: {}", e);
+                        return None;
+                    }
+                }
+            }
+            self.chord = Some(PendingChord {
+                keys,
+                deadline: Instant::now() + self.config.get_chord_timeout(),
+            });
+        } else if pending {
+            info!("Key sequence aborted");
+            self.ungrab_keyboard();
+        }
+        None
+    }
+
     /// Tries to resolve an event into an action
     ///
     /// The following actions will cause the mode of the window manager to change:
     ///  - [`ActionType::FloatingWindowMove`]
-    ///     This will change the window manager to the [`Mode::MovingWindow`] mode.
-    ///     This will cause all [`Event::PointerMotion`] events to be
-    ///     processed as a [`ActionType::FloatingWindowMove`] action.
+    ///    This will change the window manager to the [`Mode::MovingWindow`] mode.
+    ///    This will cause all [`Event::PointerMotion`] events to be
+    ///    processed as a [`ActionType::FloatingWindowMove`] action.
+    ///  - [`ActionType::FloatingWindowResize`]
+    ///    This will change the window manager to the [`Mode::ResizingWindow`] mode.
+    ///    This will cause all [`Event::PointerMotion`] events to be
+    ///    processed as a [`ActionType::FloatingWindowResize`] action.
     fn event_to_action(&mut self, event: events::Event) -> Option<actions::Action> {
         match &event {
             Event::ButtonPress(e) => {
+                self.tap = None;
                 if let Mode::None = self.mode {
                     for action in self.config.get_actions() {
+                        if !self.is_active(action.get_context()) {
+                            continue;
+                        }
                         if let Some(b) = action.get_mousebind() {
                             if b.get_modifier_mask() == e.state() as u32
                                 && b.get_button() as u8 == e.detail()
                             {
                                 let ty = action.get_type();
-                                if let ActionType::FloatingWindowMove = ty {
-                                    self.mode = Mode::MovingWindow;
+                                match ty {
+                                    ActionType::FloatingWindowMove => {
+                                        self.mode = Mode::MovingWindow(e.detail());
+                                    }
+                                    ActionType::FloatingWindowResize => {
+                                        self.mode = Mode::ResizingWindow(e.detail());
+                                    }
+                                    _ => {}
                                 }
                                 return Some(Action::new(ty, event));
                             }
                         }
                     }
+
+                    // Clicking a titlebar button triggers its action,
+                    // while dragging anywhere else on the titlebar moves its client.
+                    let monitor = self.get_monitor_at(e.root_x(), e.root_y());
+                    let desktop = self.monitors[monitor].get_active_desktop();
+                    if e.detail() == xcb::BUTTON_INDEX_1 as u8 && desktop.is_titlebar(e.event()) {
+                        let ty = match desktop.get_titlebar_button(
+                            e.event(),
+                            e.event_x(),
+                            e.event_y(),
+                        ) {
+                            Ok(Some(TitlebarButton::Close)) => ActionType::WindowClose,
+                            Ok(Some(TitlebarButton::Maximize)) => ActionType::ToggleMaximize,
+                            Ok(None) => {
+                                self.mode = Mode::MovingWindow(e.detail());
+                                ActionType::FloatingWindowMove
+                            }
+                            Err(e) => {
+                                error!("Unable to find titlebar button: {}", e);
+                                return None;
+                            }
+                        };
+                        return Some(Action::new(ty, event));
+                    }
+
+                    // Clicking a tab focuses its client.
+                    if e.detail() == xcb::BUTTON_INDEX_1 as u8
+                        && desktop
+                            .get_layout_client(e.event(), e.event_x(), e.event_y())
+                            .is_some()
+                    {
+                        return Some(Action::new(ActionType::WindowFocus, event));
+                    }
                 }
             }
-            Event::ButtonRelease(e) => {
-                if let Mode::MovingWindow = self.mode {
-                    // We'll ignore modifier masks for this
-                    for action in self.config.get_actions() {
-                        if let Some(b) = action.get_mousebind() {
-                            if b.get_button() as u8 == e.detail() {
-                                let ty = action.get_type();
-                                if let ActionType::FloatingWindowMove = ty {
-                                    self.mode = Mode::None;
-                                }
-                                return Some(Action::new(ty, event));
-                            }
+            // Drags only end when the button that started them is released,
+            // so other buttons, like the scroll wheel, are ignored meanwhile.
+            Event::ButtonRelease(e) => match self.mode {
+                Mode::MovingWindow(button) if button == e.detail() => {
+                    self.mode = Mode::None;
+                    return Some(Action::new(ActionType::FloatingWindowMove, event));
+                }
+                Mode::ResizingWindow(button) if button == e.detail() => {
+                    self.mode = Mode::None;
+                    return Some(Action::new(ActionType::FloatingWindowResize, event));
+                }
+                _ => {}
+            },
+            Event::KeyPress(e) => {
+                self.tap = None;
+                for action in self.config.get_actions() {
+                    if !self.is_active(action.get_context()) {
+                        continue;
+                    }
+                    if let Some(t) = action.get_tapbind() {
+                        if t.get_modifier_mask() == e.base.state() as u32
+                            && t.get_keysym() as u32 == e.keysym()
+                        {
+                            self.tap = Some((e.base.detail(), e.base.time()));
+                            return None;
                         }
                     }
                 }
+                return self.key_to_action(e.keysym(), e.base.state() as u32, event);
             }
-            Event::PointerMotion(_) => {
-                if let Mode::MovingWindow = self.mode {
-                    return Some(Action::new(ActionType::FloatingWindowMove, event));
+            Event::KeyRelease(e) => {
+                if let Some(keys) = &self.history_keys {
+                    if keys.contains(&e.base.detail()) {
+                        return Some(Action::new(ActionType::FocusMruCommit, event));
+                    }
+                }
+
+                // Fire a tap binding if its modifier was released quickly,
+                // without any other key or button pressed in between.
+                let (keycode, time) = self.tap.take()?;
+                if keycode != e.base.detail()
+                    || e.base.time().wrapping_sub(time) > Self::TAP_TIMEOUT
+                {
+                    return None;
+                }
+                for action in self.config.get_actions() {
+                    if !self.is_active(action.get_context()) {
+                        continue;
+                    }
+                    if let Some(t) = action.get_tapbind() {
+                        if t.get_keysym() as u32 == e.keysym() {
+                            let command = action.get_command().clone();
+                            return Some(
+                                Action::new(action.get_type(), event).with_command(command),
+                            );
+                        }
+                    }
                 }
             }
+            Event::PointerMotion(_) => match self.mode {
+                Mode::MovingWindow(_) => {
+                    return Some(Action::new(ActionType::FloatingWindowMove, event));
+                }
+                Mode::ResizingWindow(_) => {
+                    return Some(Action::new(ActionType::FloatingWindowResize, event));
+                }
+                Mode::None => {}
+            },
             Event::WindowMapRequest(_) => {
                 return Some(Action::new(ActionType::WindowFocus, event));
             }
@@ -254,3 +1856,21 @@ impl WindowManager {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamp_desktop_count() {
+        assert_eq!(WindowManager::clamp_desktop_count(4), 4);
+        assert_eq!(
+            WindowManager::clamp_desktop_count(WindowManager::MAX_DESKTOPS),
+            WindowManager::MAX_DESKTOPS
+        );
+        assert_eq!(
+            WindowManager::clamp_desktop_count(u32::MAX as usize),
+            WindowManager::MAX_DESKTOPS
+        );
+    }
+}
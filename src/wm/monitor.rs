@@ -0,0 +1,166 @@
+//! Monitors, each showing one of its own desktops.
+//!
+//! Monitors are detected with [`RandR`].
+//!
+//! [`RandR`]: https://gitlab.freedesktop.org/xorg/proto/xorgproto/-/blob/master/randrproto.txt
+
+use super::desktop::Desktop;
+use super::geometry::Rectangle;
+use crate::prelude::*;
+
+/// A monitor, with its own set of desktops and its own active desktop.
+pub struct Monitor {
+    /// Area of the screen covered by the monitor.
+    geometry: Rectangle,
+    /// Part of `geometry` not reserved by docks.
+    workarea: Rectangle,
+    /// Desktops that can be shown on this monitor. There is always at least one.
+    desktops: Vec<Desktop>,
+    /// Index of the desktop currently being shown.
+    active: usize,
+}
+
+impl Monitor {
+    /// Create a monitor showing the first of `desktops`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `desktops` is empty.
+    #[must_use]
+    pub fn new(geometry: Rectangle, desktops: Vec<Desktop>) -> Self {
+        assert!(!desktops.is_empty(), "a monitor needs at least one desktop");
+        Self {
+            geometry,
+            workarea: geometry,
+            desktops,
+            active: 0,
+        }
+    }
+
+    /// Get the area of the screen covered by this monitor.
+    pub fn get_geometry(&self) -> Rectangle {
+        self.geometry
+    }
+
+    /// Get the part of this monitor not reserved by docks.
+    pub fn get_workarea(&self) -> Rectangle {
+        self.workarea
+    }
+
+    /// Get the desktops of this monitor.
+    pub fn get_desktops(&self) -> &[Desktop] {
+        &self.desktops
+    }
+
+    /// Get the desktops of this monitor, mutably.
+    pub fn get_desktops_mut(&mut self) -> &mut [Desktop] {
+        &mut self.desktops
+    }
+
+    /// Get the index of the desktop being shown.
+    pub fn get_active(&self) -> usize {
+        self.active
+    }
+
+    /// Get the desktop being shown.
+    pub fn get_active_desktop(&self) -> &Desktop {
+        &self.desktops[self.active]
+    }
+
+    /// Get the desktop being shown, mutably.
+    pub fn get_active_desktop_mut(&mut self) -> &mut Desktop {
+        &mut self.desktops[self.active]
+    }
+
+    /// Hide the active desktop and show the one at `index`.
+    ///
+    /// Returns `false` if there is no such desktop.
+    pub fn switch_desktop(&mut self, index: usize) -> NerdResult<bool> {
+        if index >= self.desktops.len() {
+            return Ok(false);
+        }
+        if index != self.active {
+            self.desktops[self.active].hide()?;
+            self.active = index;
+            self.desktops[index].show()?;
+        }
+        Ok(true)
+    }
+
+    /// Add a desktop after the others.
+    pub fn push_desktop(&mut self, desktop: Desktop) {
+        self.desktops.push(desktop);
+    }
+
+    /// Remove the last desktop, unless it is the only one.
+    ///
+    /// If it was being shown, the desktop before it is shown instead, so the
+    /// removed desktop is always hidden.
+    pub fn pop_desktop(&mut self) -> NerdResult<Option<Desktop>> {
+        let last = self.desktops.len() - 1;
+        if last == 0 {
+            return Ok(None);
+        }
+        if self.active == last {
+            self.switch_desktop(last - 1)?;
+        }
+        Ok(self.desktops.pop())
+    }
+
+    /// Set the part of this monitor not reserved by docks, and fit every
+    /// desktop inside it.
+    pub fn set_workarea(&mut self, workarea: Rectangle) -> NerdResult<()> {
+        self.workarea = workarea;
+        for desktop in &mut self.desktops {
+            desktop.set_area(workarea)?;
+        }
+        Ok(())
+    }
+}
+
+/// Get the area of every active monitor on the screen of `root`, from the
+/// CRTCs driving them. Monitors showing the same area are only listed once.
+///
+/// Monitors are sorted from left to right, then from top to bottom, so they
+/// keep their order no matter how the server lists them.
+///
+/// If RandR is unavailable or finds no monitor, the whole screen is
+/// treated as a single monitor.
+pub fn get_monitors(conn: &xcb::Connection, root: &xcb::Screen) -> Vec<Rectangle> {
+    let screen = Rectangle::new(0, 0, root.width_in_pixels(), root.height_in_pixels());
+
+    match query_crtcs(conn, root.root()) {
+        Ok(monitors) if !monitors.is_empty() => monitors,
+        Ok(_) => vec![screen],
+        Err(e) => {
+            warn!("Unable to query monitors, using the whole screen: {}", e);
+            vec![screen]
+        }
+    }
+}
+
+/// Get the area of every CRTC that is showing something.
+fn query_crtcs(conn: &xcb::Connection, root: xcb::Window) -> NerdResult<Vec<Rectangle>> {
+    let resources = xcb::randr::get_screen_resources_current(conn, root).get_reply()?;
+
+    // Send every request before waiting for any reply.
+    let cookies: Vec<_> = resources
+        .crtcs()
+        .iter()
+        .map(|&crtc| xcb::randr::get_crtc_info(conn, crtc, resources.config_timestamp()))
+        .collect();
+
+    let mut monitors = vec![];
+    for cookie in cookies {
+        let info = cookie.get_reply()?;
+        if info.mode() == xcb::NONE || info.width() == 0 || info.height() == 0 {
+            continue;
+        }
+        let monitor = Rectangle::new(info.x(), info.y(), info.width(), info.height());
+        if !monitors.contains(&monitor) {
+            monitors.push(monitor);
+        }
+    }
+    monitors.sort_by_key(|m| (m.x, m.y));
+    Ok(monitors)
+}
@@ -0,0 +1,133 @@
+//! A single window kept outside of desktops, which can be shown and hidden at will.
+
+use std::sync::Arc;
+
+use super::client::ClientWindow;
+use super::config::{LayoutConfig, ScratchpadConfig};
+use super::geometry::Rectangle;
+use crate::prelude::*;
+
+/// Keeps track of the scratchpad window.
+///
+/// The scratchpad floats above all desktops, centered in the work area,
+/// and is not affected by switching desktops.
+pub struct Scratchpad {
+    conn: Arc<xcb::Connection>,
+    client: Option<ClientWindow>,
+    visible: bool,
+}
+
+impl Scratchpad {
+    #[must_use]
+    pub fn new(conn: Arc<xcb::Connection>) -> Self {
+        Self {
+            conn,
+            client: None,
+            visible: false,
+        }
+    }
+
+    /// Get the scratchpad client, if its window has been created.
+    pub fn get_client(&self) -> Option<&ClientWindow> {
+        self.client.as_ref()
+    }
+
+    /// Check if the scratchpad is being shown.
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Handle the scratchpad's request to move or resize itself.
+    ///
+    /// The scratchpad is always centered, so it is only told where it is.
+    /// Returns `false` if `window` isn't the scratchpad.
+    pub fn configure_request(&self, window: xcb::Window) -> NerdResult<bool> {
+        match &self.client {
+            Some(client) if client.get_window() == window => {
+                client.notify_geometry(&self.conn)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Frame a window as the scratchpad, and show it.
+    pub fn adopt(
+        &mut self,
+        window: xcb::Window,
+        layout: &LayoutConfig,
+        config: &ScratchpadConfig,
+        area: Rectangle,
+    ) -> NerdResult<()> {
+        let client = ClientWindow::from_window(
+            &self.conn,
+            window,
+            layout.get_border_focused(),
+            layout.get_frame_background(),
+            layout.get_titlebar(),
+        )?;
+        client.map(&self.conn)?;
+        self.client = Some(client);
+        trace!("Adopted scratchpad {}", window);
+
+        self.show(config, area)
+    }
+
+    /// Center the scratchpad in `area`, and show and focus it.
+    pub fn show(&mut self, config: &ScratchpadConfig, area: Rectangle) -> NerdResult<()> {
+        let client = match &self.client {
+            Some(c) => c,
+            None => return Ok(()),
+        };
+
+        let (width_ratio, height_ratio) = config.get_size();
+        client.configure(
+            &self.conn,
+            area.center_size(
+                (area.width as f32 * width_ratio) as u16,
+                (area.height as f32 * height_ratio) as u16,
+            ),
+        )?;
+
+        xcb::map_window_checked(&self.conn, client.get_frame()).request_check()?;
+        client.raise(&self.conn)?;
+        client.set_input_focus(&self.conn)?;
+        self.visible = true;
+        Ok(())
+    }
+
+    /// Hide the scratchpad, without destroying its window.
+    pub fn hide(&mut self) -> NerdResult<()> {
+        if let Some(client) = &self.client {
+            client.unmap(&self.conn)?;
+        }
+        self.visible = false;
+        Ok(())
+    }
+
+    /// Stop managing the scratchpad if `window` is its window, so the next
+    /// toggle creates a new one.
+    ///
+    /// Pass `destroyed` if the window no longer exists.
+    /// Returns `false` if `window` isn't the scratchpad.
+    pub fn forget(&mut self, window: xcb::Window, destroyed: bool) -> NerdResult<bool> {
+        match self.client {
+            Some(client) if client.get_window() == window => {
+                self.client = None;
+                self.visible = false;
+                client.release(&self.conn, destroyed)?;
+                trace!("Forgot scratchpad {}", window);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Stop managing the scratchpad window, if there is one.
+    pub fn release(&mut self) -> NerdResult<()> {
+        match self.client {
+            Some(client) => self.forget(client.get_window(), false).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Snapshots of the window manager's state, for scripts and status bars.
+//!
+//! A snapshot is returned as JSON by the `dump-state` command of the
+//! control socket. Windows are identified by their X window id.
+
+use serde::Serialize;
+
+use super::geometry::Rectangle;
+
+/// A managed client.
+#[derive(Serialize, Debug)]
+pub struct ClientSnapshot {
+    window: xcb::Window,
+    title: Option<String>,
+    /// Class name from `WM_CLASS`.
+    class: Option<String>,
+    /// Geometry of the client, including its frame.
+    geometry: Rectangle,
+    floating: bool,
+    maximized: bool,
+    focused: bool,
+}
+
+impl ClientSnapshot {
+    #[must_use]
+    pub fn new(
+        window: xcb::Window,
+        title: Option<String>,
+        class: Option<String>,
+        geometry: Rectangle,
+        floating: bool,
+        maximized: bool,
+        focused: bool,
+    ) -> Self {
+        Self {
+            window,
+            title,
+            class,
+            geometry,
+            floating,
+            maximized,
+            focused,
+        }
+    }
+}
+
+/// A desktop and the clients on it.
+#[derive(Serialize, Debug)]
+pub struct DesktopSnapshot {
+    /// Index of the desktop across all monitors, as used by `_NET_CURRENT_DESKTOP`.
+    index: usize,
+    name: String,
+    /// Clients in stacking order, with the master client first.
+    clients: Vec<ClientSnapshot>,
+}
+
+impl DesktopSnapshot {
+    #[must_use]
+    pub fn new(index: usize, name: String, clients: Vec<ClientSnapshot>) -> Self {
+        Self {
+            index,
+            name,
+            clients,
+        }
+    }
+}
+
+/// A monitor and its desktops.
+#[derive(Serialize, Debug)]
+pub struct MonitorSnapshot {
+    geometry: Rectangle,
+    /// Position of the shown desktop in `desktops`.
+    active: usize,
+    focused: bool,
+    desktops: Vec<DesktopSnapshot>,
+}
+
+impl MonitorSnapshot {
+    #[must_use]
+    pub fn new(
+        geometry: Rectangle,
+        active: usize,
+        focused: bool,
+        desktops: Vec<DesktopSnapshot>,
+    ) -> Self {
+        Self {
+            geometry,
+            active,
+            focused,
+            desktops,
+        }
+    }
+}
+
+/// State of the whole window manager.
+#[derive(Serialize, Debug)]
+pub struct Snapshot {
+    monitors: Vec<MonitorSnapshot>,
+}
+
+impl Snapshot {
+    #[must_use]
+    pub fn new(monitors: Vec<MonitorSnapshot>) -> Self {
+        Self { monitors }
+    }
+
+    /// Serialize the snapshot to a single line of JSON.
+    pub fn to_json(&self) -> String {
+        // Only strings and numbers are serialized, which can't fail.
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
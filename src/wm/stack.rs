@@ -0,0 +1,180 @@
+//! A stack of clients that can be looked up by any window they own.
+
+use std::collections::HashMap;
+use std::ops::{Deref, Index, IndexMut};
+
+/// Something that owns one or more windows, like a client along with its
+/// frame and titlebar.
+pub trait OwnsWindows {
+    /// Get every window owned. These must not change while in a [`Stack`].
+    fn get_owned_windows(&self) -> Vec<xcb::Window>;
+}
+
+/// An ordered list of items, indexed by every window they own.
+///
+/// The index is rebuilt whenever items are added, removed or reordered,
+/// so looking up an item never touches the X server.
+pub struct Stack<T> {
+    items: Vec<T>,
+    /// Position of each item in `items`, keyed by every window it owns.
+    index: HashMap<xcb::Window, usize>,
+}
+
+impl<T: OwnsWindows> Stack<T> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            items: vec![],
+            index: HashMap::new(),
+        }
+    }
+
+    /// Find the position of the item owning `window`.
+    pub fn position(&self, window: xcb::Window) -> Option<usize> {
+        self.index.get(&window).copied()
+    }
+
+    /// Insert an item at position `p`, moving the ones after it down.
+    pub fn insert(&mut self, p: usize, item: T) {
+        self.items.insert(p, item);
+        self.reindex();
+    }
+
+    /// Add an item to the bottom of the stack.
+    pub fn push(&mut self, item: T) {
+        self.items.push(item);
+        self.reindex();
+    }
+
+    /// Remove the item at position `p`, moving the ones after it up.
+    pub fn remove(&mut self, p: usize) -> T {
+        let item = self.items.remove(p);
+        self.reindex();
+        item
+    }
+
+    /// Swap the items at positions `a` and `b`.
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.items.swap(a, b);
+        self.reindex();
+    }
+
+    /// Remove every item, from the top of the stack down.
+    pub fn take_all(&mut self) -> Vec<T> {
+        self.index.clear();
+        std::mem::take(&mut self.items)
+    }
+
+    /// Get the items mutably. Their windows must stay the same.
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.items.iter_mut()
+    }
+
+    /// Rebuild the index of item positions.
+    fn reindex(&mut self) {
+        self.index.clear();
+        for (p, item) in self.items.iter().enumerate() {
+            for window in item.get_owned_windows() {
+                self.index.insert(window, p);
+            }
+        }
+    }
+}
+
+impl<T: OwnsWindows> Default for Stack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Deref for Stack<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.items
+    }
+}
+
+impl<T> Index<usize> for Stack<T> {
+    type Output = T;
+
+    fn index(&self, p: usize) -> &T {
+        &self.items[p]
+    }
+}
+
+impl<T> IndexMut<usize> for Stack<T> {
+    fn index_mut(&mut self, p: usize) -> &mut T {
+        &mut self.items[p]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A client owning a client window and a frame.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Client(xcb::Window, xcb::Window);
+
+    impl OwnsWindows for Client {
+        fn get_owned_windows(&self) -> Vec<xcb::Window> {
+            vec![self.0, self.1]
+        }
+    }
+
+    /// Check that every window in the stack maps to its client's position,
+    /// and nothing else is indexed.
+    fn assert_consistent(stack: &Stack<Client>) {
+        assert_eq!(stack.index.len(), stack.len() * 2);
+        for (p, client) in stack.iter().enumerate() {
+            assert_eq!(stack.position(client.0), Some(p));
+            assert_eq!(stack.position(client.1), Some(p));
+        }
+    }
+
+    fn new_stack() -> Stack<Client> {
+        let mut stack = Stack::new();
+        for window in 1..=4 {
+            stack.insert(0, Client(window, window + 100));
+            assert_consistent(&stack);
+        }
+        stack
+    }
+
+    #[test]
+    fn insert_and_push() {
+        let mut stack = new_stack();
+        stack.push(Client(5, 105));
+        assert_consistent(&stack);
+        assert_eq!(stack.position(104), Some(0));
+        assert_eq!(stack.position(5), Some(4));
+    }
+
+    #[test]
+    fn swap() {
+        let mut stack = new_stack();
+        stack.swap(0, 2);
+        assert_consistent(&stack);
+        assert_eq!(stack.position(2), Some(0));
+        assert_eq!(stack.position(104), Some(2));
+    }
+
+    #[test]
+    fn remove() {
+        let mut stack = new_stack();
+        assert_eq!(stack.remove(1), Client(3, 103));
+        assert_consistent(&stack);
+        assert_eq!(stack.position(3), None);
+        assert_eq!(stack.position(103), None);
+        assert_eq!(stack.position(1), Some(2));
+    }
+
+    #[test]
+    fn take_all() {
+        let mut stack = new_stack();
+        assert_eq!(stack.take_all().len(), 4);
+        assert!(stack.is_empty());
+        assert_eq!(stack.position(1), None);
+    }
+}
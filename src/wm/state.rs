@@ -0,0 +1,116 @@
+//! State kept across restarts.
+//!
+//! Before the window manager restarts itself, the desktop and geometry of
+//! every client are written to `$XDG_RUNTIME_DIR/nerdwm.state`. The new
+//! instance reads the file back to put the clients where they were.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::geometry::Rectangle;
+use crate::prelude::*;
+
+/// A managed client, as it was before a restart.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SavedClient {
+    window: xcb::Window,
+    desktop: usize,
+    floating: bool,
+    /// Geometry of the client when it isn't maximized.
+    geometry: Option<Rectangle>,
+}
+
+impl SavedClient {
+    #[must_use]
+    pub fn new(
+        window: xcb::Window,
+        desktop: usize,
+        floating: bool,
+        geometry: Option<Rectangle>,
+    ) -> Self {
+        Self {
+            window,
+            desktop,
+            floating,
+            geometry,
+        }
+    }
+
+    /// Get the index of the desktop the client was on.
+    pub fn get_desktop(&self) -> usize {
+        self.desktop
+    }
+
+    /// Check if the client was kept out of the layout.
+    pub fn is_floating(&self) -> bool {
+        self.floating
+    }
+
+    /// Get the geometry of the client when it isn't maximized.
+    pub fn get_geometry(&self) -> Option<Rectangle> {
+        self.geometry
+    }
+}
+
+/// Clients managed before a restart.
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct SavedState {
+    clients: Vec<SavedClient>,
+}
+
+impl SavedState {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a client to the state.
+    pub fn push(&mut self, client: SavedClient) {
+        self.clients.push(client);
+    }
+
+    /// Find the saved state of a client window.
+    pub fn get(&self, window: xcb::Window) -> Option<&SavedClient> {
+        self.clients.iter().find(|c| c.window == window)
+    }
+
+    /// Get the path of the state file.
+    fn get_path() -> NerdResult<PathBuf> {
+        Ok(xdg::BaseDirectories::new()
+            .map_err(|e| Error::Other(e.to_string()))?
+            .get_runtime_directory()
+            .map_err(|e| Error::Other(e.to_string()))?
+            .join("nerdwm.state"))
+    }
+
+    /// Write the state file, replacing any existing one.
+    pub fn save(&self) -> NerdResult<()> {
+        let contents = toml::to_string(self)
+            .map_err(|e| Error::Other(format!("unable to serialize state: {}", e)))?;
+        std::fs::write(Self::get_path()?, contents)?;
+        Ok(())
+    }
+
+    /// Read the state file, if there is one.
+    pub fn load() -> NerdResult<Option<Self>> {
+        let path = Self::get_path()?;
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = std::fs::read_to_string(&path)?;
+        toml::from_str(&contents)
+            .map(Some)
+            .map_err(|e| Error::Other(format!("invalid state in {:?}: {}", path, e)))
+    }
+
+    /// Delete the state file, once it has been restored.
+    pub fn remove() -> NerdResult<()> {
+        let path = Self::get_path()?;
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
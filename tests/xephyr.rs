@@ -0,0 +1,211 @@
+//! Run NerdWM on a headless X server, open a couple of clients and check that
+//! they end up mapped, framed and laid out side by side.
+//!
+//! Uses Xvfb if it is installed, and Xephyr otherwise, so this is ignored by
+//! default. Run it with `cargo test --test xephyr -- --ignored`.
+
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// How long to wait for the server and the window manager to catch up.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A child process that is killed when dropped, so nothing is left running
+/// when a check fails.
+struct Process(Child);
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Get a display number that no server is using.
+fn get_free_display() -> String {
+    let num = (100..)
+        .find(|n| {
+            !Path::new(&format!("/tmp/.X11-unix/X{}", n)).exists()
+                && !Path::new(&format!("/tmp/.X{}-lock", n)).exists()
+        })
+        .unwrap();
+    format!(":{}", num)
+}
+
+/// Start a headless X server on `display`.
+fn start_server(display: &str) -> Process {
+    let server = Command::new("Xvfb")
+        .args([display, "-screen", "0", "800x600x24"])
+        .spawn()
+        .or_else(|_| {
+            Command::new("Xephyr")
+                .args([display, "-ac", "-screen", "800x600"])
+                .spawn()
+        })
+        .expect("neither Xvfb nor Xephyr could be started");
+    Process(server)
+}
+
+/// Start NerdWM on `display` with the default configuration, keeping its
+/// config, logs, control socket and saved state in `dir`.
+fn start_wm(display: &str, dir: &Path) -> Process {
+    let runtime_dir = dir.join("runtime");
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&runtime_dir)
+        .unwrap();
+    let wm = Command::new(env!("CARGO_BIN_EXE_nerdwm"))
+        .env("DISPLAY", display)
+        .env("XDG_CONFIG_HOME", dir)
+        .env("XDG_CONFIG_DIRS", dir)
+        .env("XDG_CACHE_HOME", dir)
+        .env("XDG_RUNTIME_DIR", &runtime_dir)
+        .spawn()
+        .unwrap();
+    Process(wm)
+}
+
+/// Call `f` until it returns `true`, or the timeout runs out.
+fn wait_for(mut f: impl FnMut() -> bool) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < TIMEOUT {
+        if f() {
+            return true;
+        }
+        sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+/// Get the geometry of a window in root coordinates.
+fn get_root_geometry(conn: &xcb::Connection, root: xcb::Window, window: xcb::Window) -> [i32; 4] {
+    let geometry = xcb::get_geometry(conn, window).get_reply().unwrap();
+    let position = xcb::translate_coordinates(conn, window, root, 0, 0)
+        .get_reply()
+        .unwrap();
+    [
+        position.dst_x() as i32,
+        position.dst_y() as i32,
+        geometry.width() as i32,
+        geometry.height() as i32,
+    ]
+}
+
+/// Get the 32-bit values of a property of type `ty` on `window`.
+fn get_property_32(
+    conn: &xcb::Connection,
+    window: xcb::Window,
+    name: &str,
+    ty: xcb::Atom,
+) -> Vec<u32> {
+    let property = xcb::intern_atom(conn, false, name)
+        .get_reply()
+        .unwrap()
+        .atom();
+    xcb::get_property(conn, false, window, property, ty, 0, u32::MAX)
+        .get_reply()
+        .unwrap()
+        .value::<u32>()
+        .to_vec()
+}
+
+/// Check if a window manager is running, from the `_NET_SUPPORTING_WM_CHECK`
+/// window it sets on the root window once it is ready.
+fn is_managed(conn: &xcb::Connection, root: xcb::Window) -> bool {
+    !get_property_32(conn, root, "_NET_SUPPORTING_WM_CHECK", xcb::ATOM_WINDOW).is_empty()
+}
+
+#[test]
+#[ignore = "needs Xvfb or Xephyr"]
+fn clients_are_managed() {
+    let display = get_free_display();
+    let _server = start_server(&display);
+
+    let mut conn = None;
+    assert!(
+        wait_for(|| {
+            conn = xcb::Connection::connect(Some(&display)).ok();
+            conn.is_some()
+        }),
+        "server did not start"
+    );
+    let (conn, screen_num) = conn.unwrap();
+    let screen = conn.get_setup().roots().nth(screen_num as usize).unwrap();
+    let (root, root_visual) = (screen.root(), screen.root_visual());
+    let (screen_width, screen_height) = (
+        screen.width_in_pixels() as i32,
+        screen.height_in_pixels() as i32,
+    );
+
+    let dir: PathBuf = std::env::temp_dir().join(format!("nerdwm-test{}", display));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut wm = start_wm(&display, &dir);
+    assert!(
+        wait_for(|| is_managed(&conn, root)),
+        "window manager did not start"
+    );
+
+    let clients: Vec<xcb::Window> = (0..2)
+        .map(|_| {
+            let window = conn.generate_id();
+            xcb::create_window(
+                &conn,
+                xcb::COPY_FROM_PARENT as u8,
+                window,
+                root,
+                0,
+                0,
+                100,
+                100,
+                0,
+                xcb::WINDOW_CLASS_INPUT_OUTPUT as u16,
+                root_visual,
+                &[],
+            );
+            xcb::map_window(&conn, window);
+            window
+        })
+        .collect();
+    conn.flush();
+
+    for &window in &clients {
+        assert!(
+            wait_for(|| {
+                xcb::get_window_attributes(&conn, window)
+                    .get_reply()
+                    .is_ok_and(|a| a.map_state() == xcb::MAP_STATE_VIEWABLE as u8)
+            }),
+            "{} was not mapped",
+            window
+        );
+        let parent = xcb::query_tree(&conn, window).get_reply().unwrap().parent();
+        assert_ne!(parent, root, "{} was not framed", window);
+    }
+
+    let client_list = get_property_32(&conn, root, "_NET_CLIENT_LIST", xcb::ATOM_WINDOW);
+    for window in &clients {
+        assert!(
+            client_list.contains(window),
+            "{} is not in _NET_CLIENT_LIST",
+            window
+        );
+    }
+
+    // Tiled clients should be inside the screen, and not overlap.
+    let geometries: Vec<[i32; 4]> = clients
+        .iter()
+        .map(|&w| get_root_geometry(&conn, root, w))
+        .collect();
+    for &[x, y, width, height] in &geometries {
+        assert!(x >= 0 && y >= 0 && x + width <= screen_width && y + height <= screen_height);
+    }
+    let ([x1, y1, w1, h1], [x2, y2, w2, h2]) = (geometries[0], geometries[1]);
+    let overlap = x1 < x2 + w2 && x2 < x1 + w1 && y1 < y2 + h2 && y2 < y1 + h1;
+    assert!(!overlap, "clients are not laid out: {:?}", geometries);
+
+    assert!(wm.0.try_wait().unwrap().is_none(), "nerdwm exited");
+}